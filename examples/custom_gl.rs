@@ -0,0 +1,82 @@
+use gtk::{prelude::*, Application, ApplicationWindow};
+use gtk_egui_area::{
+    egui,
+    egui_glow::glow::{self, HasContext},
+    paint_callback, EguiArea,
+};
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+
+fn main() {
+    let app = Application::builder().build();
+
+    app.connect_activate(build_ui);
+
+    app.run();
+}
+
+fn build_ui(app: &Application) {
+    let window = ApplicationWindow::new(app);
+    window.set_default_width(800);
+    window.set_default_height(600);
+
+    let hue = Arc::new(AtomicU32::new(0.5f32.to_bits()));
+
+    let egui_area = EguiArea::new({
+        let hue = hue.clone();
+        move |ctx| {
+            egui::SidePanel::left("controls").show(ctx, |ui| {
+                let mut value = f32::from_bits(hue.load(Ordering::Relaxed));
+                ui.label("Hue");
+                ui.add(egui::Slider::new(&mut value, 0.0..=1.0));
+                hue.store(value.to_bits(), Ordering::Relaxed);
+            });
+
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ui.label("The rectangle below is drawn with OpenGL:");
+                let (rect, _) =
+                    ui.allocate_exact_size(egui::vec2(200.0, 200.0), egui::Sense::hover());
+
+                let hue = hue.clone();
+                ui.painter().add(paint_callback(rect, move |info, painter| {
+                    let color = egui::Rgba::from(egui::ecolor::Hsva::new(
+                        f32::from_bits(hue.load(Ordering::Relaxed)),
+                        0.8,
+                        0.8,
+                        1.0,
+                    ));
+                    let viewport = info.viewport_in_pixels();
+                    let gl = painter.gl();
+                    unsafe {
+                        gl.scissor(
+                            viewport.left_px,
+                            viewport.from_bottom_px,
+                            viewport.width_px,
+                            viewport.height_px,
+                        );
+                        gl.clear_color(color.r(), color.g(), color.b(), 1.0);
+                        gl.clear(glow::COLOR_BUFFER_BIT);
+                    }
+                }));
+            });
+        }
+    });
+
+    egui_area.set_post_render(|gl, info| {
+        // A thin bar along the bottom edge of the area, drawn over the UI
+        let viewport = info.viewport_in_pixels();
+        unsafe {
+            gl.enable(glow::SCISSOR_TEST);
+            gl.scissor(0, 0, viewport.width_px, 4);
+            gl.clear_color(0.2, 0.4, 0.9, 1.0);
+            gl.clear(glow::COLOR_BUFFER_BIT);
+            gl.disable(glow::SCISSOR_TEST);
+        }
+    });
+
+    window.set_child(Some(&egui_area));
+
+    window.present();
+}
@@ -0,0 +1,71 @@
+use gtk::{prelude::*, Application, ApplicationWindow};
+use gtk_egui_area::EguiPaintable;
+
+fn main() {
+    let app = Application::builder().build();
+
+    app.connect_activate(build_ui);
+
+    app.run();
+}
+
+fn build_ui(app: &Application) {
+    let window = ApplicationWindow::new(app);
+    window.set_default_width(400);
+    window.set_default_height(600);
+
+    // All rows draw with one egui context, so they share the font atlas and the GL context
+    let ctx = egui::Context::default();
+
+    let factory = gtk::SignalListItemFactory::new();
+    factory.connect_setup(move |_, item| {
+        let item = item.downcast_ref::<gtk::ListItem>().unwrap();
+
+        let paintable = EguiPaintable::with_context(|_ctx| {}, ctx.clone());
+        paintable.set_size(0, 32);
+
+        let picture = gtk::Picture::for_paintable(&paintable);
+        // `set_content_fit` needs GTK 4.8
+        #[allow(deprecated)]
+        picture.set_keep_aspect_ratio(false);
+        picture.set_focusable(true);
+        paintable.attach(&picture);
+
+        item.set_child(Some(&picture));
+    });
+    factory.connect_bind(|_, item| {
+        let item = item.downcast_ref::<gtk::ListItem>().unwrap();
+        let index = item.position();
+        let paintable = item
+            .child()
+            .and_downcast::<gtk::Picture>()
+            .and_then(|picture| picture.paintable())
+            .and_downcast::<EguiPaintable>()
+            .unwrap();
+
+        // Rows are reused while scrolling, so the paintable gets the UI of its new item
+        paintable.set_ui(move |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ui.horizontal_centered(|ui| {
+                    ui.label(format!("Row {index}"));
+                    if ui.button("Click me").clicked() {
+                        println!("Clicked row {index}");
+                    }
+                });
+            });
+        });
+    });
+
+    let model = gtk::NoSelection::new(Some(gtk::StringList::from_iter(
+        (0..10_000).map(|index| index.to_string()),
+    )));
+    let list_view = gtk::ListView::new(Some(model), Some(factory));
+
+    let scrolled_window = gtk::ScrolledWindow::builder()
+        .hscrollbar_policy(gtk::PolicyType::Never)
+        .child(&list_view)
+        .build();
+    window.set_child(Some(&scrolled_window));
+
+    window.present();
+}
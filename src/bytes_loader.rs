@@ -0,0 +1,127 @@
+use egui::{
+    load::{Bytes, BytesLoadResult, BytesPoll, LoadError},
+    mutex::Mutex,
+};
+use gtk::{
+    gio::{self, prelude::FileExt, prelude::VfsExt},
+    glib,
+};
+use std::{collections::HashMap, sync::Arc, task::Poll};
+
+type Entry = Poll<Result<(Bytes, Option<String>), String>>;
+
+/// An [`egui::load::BytesLoader`] that loads URIs with gio,
+/// such as `resource:///` URIs of bundled GResources, `file://` URIs and the other URI schemes supported by gio.
+///
+/// Files are loaded asynchronously on the default main context. It is installed on the context of every [`crate::EguiArea`].
+pub struct GioBytesLoader {
+    cache: Arc<Mutex<HashMap<String, Entry>>>,
+    schemes: Vec<String>,
+}
+
+impl Default for GioBytesLoader {
+    fn default() -> Self {
+        Self {
+            cache: Arc::default(),
+            schemes: gio::Vfs::default()
+                .supported_uri_schemes()
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+        }
+    }
+}
+
+impl GioBytesLoader {
+    pub const ID: &'static str = egui::generate_loader_id!(GioBytesLoader);
+
+    /// Install the loader on `ctx`, unless it's already installed.
+    pub fn install(ctx: &egui::Context) {
+        if !ctx.is_loader_installed(Self::ID) {
+            ctx.add_bytes_loader(Arc::new(Self::default()));
+        }
+    }
+}
+
+impl egui::load::BytesLoader for GioBytesLoader {
+    fn id(&self) -> &str {
+        Self::ID
+    }
+
+    fn load(&self, ctx: &egui::Context, uri: &str) -> BytesLoadResult {
+        let supported = uri
+            .split_once("://")
+            .is_some_and(|(scheme, _)| self.schemes.iter().any(|supported| supported == scheme));
+        if !supported {
+            return Err(LoadError::NotSupported);
+        }
+
+        let mut cache = self.cache.lock();
+        if let Some(entry) = cache.get(uri) {
+            return match entry {
+                Poll::Ready(Ok((bytes, mime))) => Ok(BytesPoll::Ready {
+                    size: None,
+                    bytes: bytes.clone(),
+                    mime: mime.clone(),
+                }),
+                Poll::Ready(Err(err)) => Err(LoadError::Loading(err.clone())),
+                Poll::Pending => Ok(BytesPoll::Pending { size: None }),
+            };
+        }
+        cache.insert(uri.to_owned(), Poll::Pending);
+        drop(cache);
+
+        let cache = self.cache.clone();
+        let ctx = ctx.clone();
+        let uri = uri.to_owned();
+        // Loaders may be called from any thread, while the file has to be loaded on the thread of the main context
+        glib::MainContext::default().invoke(move || {
+            glib::MainContext::default().spawn_local(async move {
+                let file = gio::File::for_uri(&uri);
+                let result = match file.load_bytes_future().await {
+                    Ok((bytes, _etag)) => {
+                        let (content_type, _uncertain) =
+                            gio::content_type_guess(file.basename(), &bytes);
+                        let mime = gio::content_type_get_mime_type(&content_type);
+                        Ok((Bytes::Shared(bytes.to_vec().into()), mime.map(Into::into)))
+                    }
+                    Err(err) => Err(err.to_string()),
+                };
+
+                // The entry is gone if the URI was forgotten while loading
+                if let Some(entry) = cache.lock().get_mut(&uri) {
+                    *entry = Poll::Ready(result);
+                }
+                ctx.request_repaint();
+            });
+        });
+
+        Ok(BytesPoll::Pending { size: None })
+    }
+
+    fn forget(&self, uri: &str) {
+        self.cache.lock().remove(uri);
+    }
+
+    fn forget_all(&self) {
+        self.cache.lock().clear();
+    }
+
+    fn byte_size(&self) -> usize {
+        self.cache
+            .lock()
+            .values()
+            .map(|entry| match entry {
+                Poll::Ready(Ok((bytes, _))) => bytes.len(),
+                Poll::Ready(Err(err)) => err.len(),
+                Poll::Pending => 0,
+            })
+            .sum()
+    }
+}
+
+/// Load a font from a URI supported by gio, such as a `resource:///` URI, for use in [`egui::FontDefinitions`].
+pub fn load_font_data(uri: &str) -> Result<egui::FontData, glib::Error> {
+    let (bytes, _etag) = gio::File::for_uri(uri).load_bytes(None::<&gio::Cancellable>)?;
+    Ok(egui::FontData::from_owned(bytes.to_vec()))
+}
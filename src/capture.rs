@@ -0,0 +1,27 @@
+use gtk::{gdk, glib, prelude::TextureExt};
+use std::path::Path;
+
+/// Create a texture with the pixels of an egui image, e.g. one returned by [`crate::EguiArea::capture`].
+pub fn image_to_texture(image: &egui::ColorImage) -> gdk::Texture {
+    let [width, height] = image.size;
+    let bytes: Vec<u8> = image
+        .pixels
+        .iter()
+        .flat_map(|pixel| pixel.to_array())
+        .collect();
+
+    // egui colors are premultiplied
+    gdk::MemoryTexture::new(
+        width as i32,
+        height as i32,
+        gdk::MemoryFormat::R8g8b8a8Premultiplied,
+        &glib::Bytes::from_owned(bytes),
+        width * 4,
+    )
+    .into()
+}
+
+/// Save an egui image as a PNG file.
+pub fn save_png(image: &egui::ColorImage, path: impl AsRef<Path>) -> Result<(), glib::BoolError> {
+    image_to_texture(image).save_to_png(path)
+}
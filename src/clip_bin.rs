@@ -0,0 +1,90 @@
+use gtk::{
+    glib,
+    prelude::{Cast, IsA, WidgetExt},
+    subclass::prelude::ObjectSubclassIsExt,
+};
+
+glib::wrapper! {
+    /// A widget that shows a GTK widget embedded in egui, allocated at the clip rect of the widget.
+    ///
+    /// Its overflow is hidden, so the parts of the widget outside of the clip rect are neither drawn nor picked,
+    /// e.g. when the widget is scrolled out of an [`egui::ScrollArea`].
+    pub struct ClipBin(ObjectSubclass<imp::ClipBin>)
+        @extends gtk::Widget;
+}
+
+impl ClipBin {
+    pub(crate) fn new(child: &impl IsA<gtk::Widget>) -> Self {
+        let bin: Self = glib::Object::new();
+        bin.set_overflow(gtk::Overflow::Hidden);
+        child.set_parent(&bin);
+        bin.imp().child.replace(Some(child.clone().upcast()));
+        bin
+    }
+
+    pub(crate) fn child(&self) -> Option<gtk::Widget> {
+        self.imp().child.borrow().clone()
+    }
+
+    /// Set where the child is allocated, relative to the bin.
+    pub(crate) fn set_child_rect(&self, rect: egui::Rect) {
+        self.imp().child_rect.set(Some(rect));
+    }
+}
+
+mod imp {
+    use gtk::{
+        glib,
+        prelude::WidgetExt,
+        subclass::{
+            prelude::{ObjectImpl, ObjectSubclass},
+            widget::WidgetImpl,
+        },
+    };
+    use std::cell::{Cell, RefCell};
+
+    #[derive(Default)]
+    pub struct ClipBin {
+        pub(super) child: RefCell<Option<gtk::Widget>>,
+        pub(super) child_rect: Cell<Option<egui::Rect>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for ClipBin {
+        const NAME: &'static str = "GtkEguiAreaClipBin";
+        type Type = super::ClipBin;
+        type ParentType = gtk::Widget;
+    }
+
+    impl ObjectImpl for ClipBin {
+        fn dispose(&self) {
+            if let Some(child) = self.child.take() {
+                child.unparent();
+            }
+        }
+    }
+
+    impl WidgetImpl for ClipBin {
+        fn measure(&self, _orientation: gtk::Orientation, _for_size: i32) -> (i32, i32, i32, i32) {
+            // The area allocates the bin at the clip rect, whatever the size of the child is
+            (0, 0, -1, -1)
+        }
+
+        fn size_allocate(&self, _width: i32, _height: i32, _baseline: i32) {
+            let (Some(child), Some(rect)) = (self.child.borrow().clone(), self.child_rect.get())
+            else {
+                return;
+            };
+
+            let (min_width, ..) = child.measure(gtk::Orientation::Horizontal, -1);
+            let (min_height, ..) = child.measure(gtk::Orientation::Vertical, -1);
+            let allocation = gtk::Allocation::new(
+                rect.min.x.round() as i32,
+                rect.min.y.round() as i32,
+                (rect.width().round() as i32).max(min_width),
+                (rect.height().round() as i32).max(min_height),
+            );
+            child.size_allocate(&allocation, -1);
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use egui_glow::glow::{self, HasContext};
+use std::{num::NonZeroU32, sync::Arc};
+
+/// A function that draws with OpenGL over the whole area, before or after the egui UI.
+pub(crate) type DynRenderHook = Box<dyn Fn(&glow::Context, &egui::PaintCallbackInfo)>;
+
+/// Create an [`egui::PaintCallback`] that draws with OpenGL inside of `rect`.
+///
+/// The callback is run with the GL viewport set to `rect` and the scissor set to the clip rect of the shape.
+/// It may bind its own framebuffers, the framebuffer of the [`crate::EguiArea`] is bound again once it returns.
+pub fn paint_callback(
+    rect: egui::Rect,
+    callback: impl Fn(egui::PaintCallbackInfo, &egui_glow::Painter) + Send + Sync + 'static,
+) -> egui::PaintCallback {
+    egui::PaintCallback {
+        rect,
+        callback: Arc::new(egui_glow::CallbackFn::new(move |info, painter| {
+            with_restored_framebuffer(painter.gl(), || callback(info, painter));
+        })),
+    }
+}
+
+/// Run `f`, binding the current framebuffer again afterwards.
+///
+/// [`gtk::GLArea`] draws into its own framebuffer instead of the default one,
+/// so custom GL code that binds other framebuffers can't simply unbind them when it's done.
+pub(crate) fn with_restored_framebuffer<R>(gl: &glow::Context, f: impl FnOnce() -> R) -> R {
+    let framebuffer = unsafe { gl.get_parameter_i32(glow::FRAMEBUFFER_BINDING) };
+    let result = f();
+    unsafe {
+        gl.bind_framebuffer(
+            glow::FRAMEBUFFER,
+            NonZeroU32::new(framebuffer as u32).map(glow::NativeFramebuffer),
+        );
+    }
+    result
+}
@@ -0,0 +1,540 @@
+use crate::{input, shared::SharedContext};
+use gtk::{
+    gdk, glib,
+    prelude::{Cast, IsA, PaintableExt, WidgetExt},
+    subclass::prelude::ObjectSubclassIsExt,
+};
+use std::rc::Rc;
+
+glib::wrapper! {
+    /// A [`gdk::Paintable`] that draws an [`egui`] UI, for showing egui in many small places,
+    /// like the rows of a [`gtk::ListView`] or the cells of a [`gtk::GridView`].
+    ///
+    /// Unlike an [`crate::EguiArea`], a paintable has no GL context of its own. The paintables and areas
+    /// with the same egui context draw with one GL context per display, and paintables hand their frames
+    /// to GTK as [`gdk::GLTexture`]s, whose GL textures are drawn into again once GTK is done with them.
+    ///
+    /// Show the paintable at its full size, e.g. in a [`gtk::Picture`] with [`gtk::ContentFit::Fill`],
+    /// and pass that widget to [`EguiPaintable::attach`] so the UI gets its input.
+    /// When a list row is bound to another item, give the paintable of the row a new UI with [`EguiPaintable::set_ui`].
+    pub struct EguiPaintable(ObjectSubclass<imp::EguiPaintable>)
+        @implements gdk::Paintable;
+}
+
+impl EguiPaintable {
+    /// Construct a new [`EguiPaintable`] with the provided egui UI function.
+    pub fn new(ui: impl Fn(&egui::Context) + 'static) -> Self {
+        let paintable: Self = glib::Object::builder().build();
+        paintable.set_ui(ui);
+        paintable
+    }
+
+    /// Construct a new [`EguiPaintable`] that draws with an existing egui context.
+    ///
+    /// The paintables of a list should share one context, so they share fonts, textures and the GL context.
+    /// Each paintable runs its UI in its own viewport, like areas created with [`crate::EguiArea::with_context`].
+    pub fn with_context(ui: impl Fn(&egui::Context) + 'static, ctx: egui::Context) -> Self {
+        let paintable: Self = glib::Object::builder().build();
+        let attached = paintable
+            .imp()
+            .shared
+            .set(SharedContext::attach(ctx))
+            .is_ok();
+        assert!(attached, "a new paintable already has an egui context");
+        paintable.set_ui(ui);
+        paintable
+    }
+
+    /// Set a new egui UI function, e.g. when the list row showing the paintable is bound to another item.
+    pub fn set_ui(&self, ui: impl Fn(&egui::Context) + 'static) {
+        *self.imp().run_ui.borrow_mut() = Some(Box::new(ui));
+        self.imp().queue_frame();
+    }
+
+    /// Set the size that the paintable prefers to be shown at, in points. `0` leaves the size up to the widget.
+    pub fn set_size(&self, width: i32, height: i32) {
+        self.imp().size.set((width, height));
+        self.invalidate_size();
+    }
+
+    /// Pass the input of the widget that shows the paintable to egui, and draw for the scale of its surface.
+    ///
+    /// GTK sends pointer input only to the widget under the pointer and keys only to the focused widget,
+    /// so only the paintables that the user interacts with get input and run new frames.
+    /// The widget must be focusable to get keys. A paintable should only be attached to one widget.
+    pub fn attach(&self, widget: &impl IsA<gtk::Widget>) {
+        let widget = widget.upcast_ref::<gtk::Widget>();
+        input::register_controllers(
+            widget,
+            Rc::new(glib::clone!(
+                #[weak(rename_to = paintable)]
+                self,
+                move |event| {
+                    let imp = paintable.imp();
+                    imp.input_events.borrow_mut().push(event);
+                    imp.queue_frame();
+                }
+            )),
+        );
+        widget.connect_scale_factor_notify(glib::clone!(
+            #[weak(rename_to = paintable)]
+            self,
+            move |_| paintable.imp().queue_frame()
+        ));
+        self.imp().widget.set(Some(widget));
+        self.imp().queue_frame();
+    }
+
+    /// Access the inner [`egui::Context`].
+    pub fn egui_ctx(&self) -> &egui::Context {
+        &self.imp().shared().ctx
+    }
+}
+
+mod imp {
+    use crate::{
+        shared::{GlPainter, SharedContext},
+        software::{self, SoftwarePainter},
+    };
+    use egui_glow::glow::{self, HasContext};
+    use gtk::{
+        gdk, glib, graphene,
+        prelude::{Cast, GLContextExt, PaintableExt, SnapshotExt, WidgetExt},
+        subclass::prelude::{
+            ObjectImpl, ObjectSubclass, ObjectSubclassExt, ObjectSubclassIsExt, PaintableImpl,
+        },
+    };
+    use std::{
+        cell::{Cell, OnceCell, RefCell},
+        rc::{Rc, Weak},
+        time::Duration,
+    };
+
+    type DynGuiFn = Box<dyn Fn(&egui::Context)>;
+
+    /// A frame that is shown until the paintable runs a new one.
+    struct Frame {
+        texture: gdk::Texture,
+        size: [u32; 2],
+        /// GL textures start at the bottom
+        flipped: bool,
+    }
+
+    /// The painter that a paintable draws with, and the GL textures that GTK is done with.
+    struct GlTarget {
+        painter: Rc<GlPainter>,
+        free_textures: RefCell<Vec<(glow::Texture, [u32; 2])>>,
+    }
+
+    #[derive(Default)]
+    pub struct EguiPaintable {
+        /// Set on first use, unless the paintable was constructed with an existing context
+        pub(super) shared: OnceCell<Rc<SharedContext>>,
+        /// The viewport that the UI runs in, set on first use
+        area_viewport: Cell<Option<egui::ViewportId>>,
+        pub(super) run_ui: RefCell<Option<DynGuiFn>>,
+        pub(super) size: Cell<(i32, i32)>,
+        pub(super) widget: glib::WeakRef<gtk::Widget>,
+        pub(super) input_events: RefCell<Vec<egui::Event>>,
+        /// Whether the UI needs to run again, even if the size stays the same
+        dirty: Cell<bool>,
+        frame: RefCell<Option<Frame>>,
+        repaint: RefCell<Option<glib::SourceId>>,
+        gl: RefCell<Option<Rc<GlTarget>>>,
+        software: RefCell<Option<SoftwarePainter>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for EguiPaintable {
+        const NAME: &'static str = "EguiPaintable";
+        type Type = super::EguiPaintable;
+        type Interfaces = (gdk::Paintable,);
+    }
+
+    impl ObjectImpl for EguiPaintable {
+        fn dispose(&self) {
+            self.run_ui.take();
+            self.frame.take();
+            if let Some(source) = self.repaint.take() {
+                source.remove();
+            }
+            self.gl.take();
+            self.software.take();
+
+            if let (Some(shared), Some(viewport_id)) =
+                (self.shared.get(), self.area_viewport.take())
+            {
+                shared.release_viewport(viewport_id);
+            }
+        }
+    }
+
+    impl PaintableImpl for EguiPaintable {
+        fn snapshot(&self, snapshot: &gdk::Snapshot, width: f64, height: f64) {
+            let Some(snapshot) = snapshot.downcast_ref::<gtk::Snapshot>() else {
+                return;
+            };
+            let (width, height) = (width as f32, height as f32);
+            self.update_frame(width, height);
+
+            let frame = self.frame.borrow();
+            let Some(frame) = frame.as_ref() else {
+                return;
+            };
+            snapshot.save();
+            if frame.flipped {
+                snapshot.translate(&graphene::Point::new(0.0, height));
+                snapshot.scale(1.0, -1.0);
+            }
+            snapshot.append_texture(
+                &frame.texture,
+                &graphene::Rect::new(0.0, 0.0, width, height),
+            );
+            snapshot.restore();
+        }
+
+        fn intrinsic_width(&self) -> i32 {
+            self.size.get().0
+        }
+
+        fn intrinsic_height(&self) -> i32 {
+            self.size.get().1
+        }
+    }
+
+    impl EguiPaintable {
+        pub(super) fn shared(&self) -> &Rc<SharedContext> {
+            self.shared
+                .get_or_init(|| SharedContext::attach(egui::Context::default()))
+        }
+
+        /// The viewport that the UI of the paintable runs in.
+        fn viewport_id(&self) -> egui::ViewportId {
+            match self.area_viewport.get() {
+                Some(viewport_id) => viewport_id,
+                None => {
+                    let viewport_id = self.shared().acquire_viewport();
+                    self.area_viewport.set(Some(viewport_id));
+                    viewport_id
+                }
+            }
+        }
+
+        /// Run the UI again the next time the paintable is drawn.
+        pub(super) fn queue_frame(&self) {
+            self.dirty.set(true);
+            self.obj().invalidate_contents();
+        }
+
+        /// Run a frame and draw it, unless the shown frame is still up to date.
+        ///
+        /// GTK draws paintables again whenever the widgets that show them are drawn,
+        /// e.g. while a list scrolls, so the UI only runs when it changed or the size did.
+        fn update_frame(&self, width: f32, height: f32) {
+            let widget = self.widget.upgrade();
+            let scale_factor = widget.as_ref().map_or(1.0, crate::scale_factor);
+            let size = [
+                (width * scale_factor).round() as u32,
+                (height * scale_factor).round() as u32,
+            ];
+            let up_to_date = !self.dirty.get()
+                && self
+                    .frame
+                    .borrow()
+                    .as_ref()
+                    .is_some_and(|frame| frame.size == size);
+            if up_to_date || size.contains(&0) {
+                return;
+            }
+            self.dirty.set(false);
+
+            let frame = self
+                .run_frame(egui::vec2(width, height), scale_factor, widget.as_ref())
+                .map(|(clipped_primitives, pixels_per_point)| {
+                    self.paint(&clipped_primitives, pixels_per_point, size, widget.as_ref())
+                });
+            *self.frame.borrow_mut() = frame;
+        }
+
+        /// Run the egui UI function for a single frame and tessellate the output,
+        /// returning `None` if there is no UI function set.
+        fn run_frame(
+            &self,
+            size: egui::Vec2,
+            scale_factor: f32,
+            widget: Option<&gtk::Widget>,
+        ) -> Option<(Vec<egui::ClippedPrimitive>, f32)> {
+            let run_ui = self.run_ui.borrow();
+            let run_ui = run_ui.as_ref()?;
+            let shared = self.shared();
+
+            let viewport_id = self.viewport_id();
+            let focused = widget.is_some_and(|widget| widget.has_focus());
+            let raw_input = egui::RawInput {
+                viewport_id,
+                viewports: std::iter::once((
+                    viewport_id,
+                    egui::ViewportInfo {
+                        native_pixels_per_point: Some(scale_factor),
+                        focused: Some(focused),
+                        ..Default::default()
+                    },
+                ))
+                .collect(),
+                screen_rect: Some(egui::Rect::from_min_size(egui::Pos2::ZERO, size)),
+                events: self.input_events.take(),
+                time: Some(glib::monotonic_time() as f64 / 1_000_000.0),
+                focused,
+                ..egui::RawInput::default()
+            };
+
+            let full_output = shared.ctx.run(raw_input, |ctx| run_ui(ctx));
+            shared
+                .textures
+                .borrow_mut()
+                .apply(&full_output.textures_delta);
+
+            if let Some(widget) = widget {
+                if !full_output.platform_output.copied_text.is_empty() {
+                    widget
+                        .clipboard()
+                        .set_text(&full_output.platform_output.copied_text);
+                }
+            }
+            if let Some(viewport) = full_output.viewport_output.get(&viewport_id) {
+                self.schedule_repaint(viewport.repaint_delay);
+            }
+
+            let pixels_per_point = full_output.pixels_per_point;
+            let clipped_primitives = shared.ctx.tessellate(full_output.shapes, pixels_per_point);
+            Some((clipped_primitives, pixels_per_point))
+        }
+
+        /// Run a frame after `delay`, when egui asked for one.
+        fn schedule_repaint(&self, delay: Duration) {
+            if delay == Duration::MAX || self.repaint.borrow().is_some() {
+                return;
+            }
+            let source = glib::timeout_add_local_once(
+                delay,
+                glib::clone!(
+                    #[weak(rename_to = paintable)]
+                    self.obj(),
+                    move || {
+                        let imp = paintable.imp();
+                        imp.repaint.take();
+                        imp.queue_frame();
+                    }
+                ),
+            );
+            self.repaint.replace(Some(source));
+        }
+
+        /// Draw tessellated output with OpenGL, or on the CPU if OpenGL is not available.
+        fn paint(
+            &self,
+            clipped_primitives: &[egui::ClippedPrimitive],
+            pixels_per_point: f32,
+            size: [u32; 2],
+            widget: Option<&gtk::Widget>,
+        ) -> Frame {
+            if self.software.borrow().is_none() {
+                let result = self
+                    .gl_target(widget)
+                    .and_then(|gl| self.paint_gl(&gl, clipped_primitives, pixels_per_point, size));
+                match result {
+                    Ok(frame) => return frame,
+                    Err(err) => {
+                        if !software::is_forced() {
+                            glib::g_warning!(
+                                "gtk-egui-area",
+                                "Could not use OpenGL for a paintable, falling back to software rendering: {err}"
+                            );
+                        }
+                        self.gl.take();
+                        self.software.replace(Some(SoftwarePainter::default()));
+                    }
+                }
+            }
+
+            let mut software = self.software.borrow_mut();
+            let software = software.get_or_insert_with(SoftwarePainter::default);
+            software.paint(
+                &self.shared().textures.borrow(),
+                size,
+                pixels_per_point,
+                egui::Color32::TRANSPARENT,
+                clipped_primitives,
+            );
+            Frame {
+                texture: crate::image_to_texture(software.frame()),
+                size,
+                flipped: false,
+            }
+        }
+
+        fn gl_target(&self, widget: Option<&gtk::Widget>) -> Result<Rc<GlTarget>, glib::Error> {
+            if software::is_forced() {
+                return Err(glib::Error::new(
+                    gdk::GLError::NotAvailable,
+                    "Software rendering was requested",
+                ));
+            }
+            if let Some(gl) = self.gl.borrow().as_ref() {
+                return Ok(gl.clone());
+            }
+
+            let display = match widget {
+                Some(widget) => widget.display(),
+                None => gdk::Display::default().ok_or_else(|| {
+                    glib::Error::new(gdk::GLError::NotAvailable, "No display to draw on")
+                })?,
+            };
+            let gl = Rc::new(GlTarget {
+                painter: self.shared().offscreen_painter(&display, widget)?,
+                free_textures: RefCell::default(),
+            });
+            self.gl.replace(Some(gl.clone()));
+            Ok(gl)
+        }
+
+        /// Draw into a GL texture and wrap it for GTK.
+        fn paint_gl(
+            &self,
+            gl_target: &Rc<GlTarget>,
+            clipped_primitives: &[egui::ClippedPrimitive],
+            pixels_per_point: f32,
+            size: [u32; 2],
+        ) -> Result<Frame, glib::Error> {
+            let gl_painter = &gl_target.painter;
+            gl_painter.context().make_current();
+            let mut painter = gl_painter.painter.borrow_mut();
+            let gl = painter.gl().clone();
+
+            self.shared()
+                .textures
+                .borrow()
+                .sync(&mut *painter, &mut gl_painter.synced_textures.borrow_mut());
+
+            let to_error = |err: String| glib::Error::new(gdk::GLError::NotAvailable, &err);
+            let texture = gl_target.take_texture(&gl, size).map_err(to_error)?;
+            unsafe {
+                let framebuffer = gl.create_framebuffer().map_err(to_error)?;
+                gl.bind_framebuffer(glow::FRAMEBUFFER, Some(framebuffer));
+                gl.framebuffer_texture_2d(
+                    glow::FRAMEBUFFER,
+                    glow::COLOR_ATTACHMENT0,
+                    glow::TEXTURE_2D,
+                    Some(texture),
+                    0,
+                );
+                painter.clear(size, [0.0; 4]);
+                painter.paint_primitives(size, pixels_per_point, clipped_primitives);
+                gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+                gl.delete_framebuffer(framebuffer);
+                // GTK draws the texture with another context
+                gl.flush();
+
+                if gl.get_error() == glow::CONTEXT_LOST {
+                    // Areas and paintables that still use the context notice when they draw with it
+                    self.shared().discard(gl_painter);
+                    self.gl.take();
+                    self.queue_frame();
+                }
+            }
+
+            let pool = Rc::downgrade(gl_target);
+            let owner = gl_painter.clone();
+            let texture = unsafe {
+                gdk::GLTexture::with_release_func(
+                    gl_painter.context(),
+                    texture.0.get(),
+                    size[0] as i32,
+                    size[1] as i32,
+                    move || release_texture(pool, &owner, texture, size),
+                )
+            };
+            Ok(Frame {
+                texture: texture.upcast(),
+                size,
+                flipped: true,
+            })
+        }
+    }
+
+    impl GlTarget {
+        /// A texture of `size` pixels to draw into, reusing one that GTK is done with if possible.
+        fn take_texture(
+            &self,
+            gl: &glow::Context,
+            size: [u32; 2],
+        ) -> Result<glow::Texture, String> {
+            let mut free_textures = self.free_textures.borrow_mut();
+            // Rows are usually all the same size, so textures of other sizes are not kept around
+            while let Some((texture, texture_size)) = free_textures.pop() {
+                if texture_size == size {
+                    return Ok(texture);
+                }
+                unsafe { gl.delete_texture(texture) };
+            }
+
+            unsafe {
+                let texture = gl.create_texture()?;
+                gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+                gl.tex_parameter_i32(
+                    glow::TEXTURE_2D,
+                    glow::TEXTURE_MIN_FILTER,
+                    glow::LINEAR as i32,
+                );
+                gl.tex_parameter_i32(
+                    glow::TEXTURE_2D,
+                    glow::TEXTURE_MAG_FILTER,
+                    glow::LINEAR as i32,
+                );
+                gl.tex_image_2d(
+                    glow::TEXTURE_2D,
+                    0,
+                    glow::RGBA8 as i32,
+                    size[0] as i32,
+                    size[1] as i32,
+                    0,
+                    glow::RGBA,
+                    glow::UNSIGNED_BYTE,
+                    None,
+                );
+                gl.bind_texture(glow::TEXTURE_2D, None);
+                Ok(texture)
+            }
+        }
+    }
+
+    impl Drop for GlTarget {
+        fn drop(&mut self) {
+            delete_textures(&self.painter, self.free_textures.take());
+        }
+    }
+
+    /// Give a texture that GTK is done with back to its paintable, or delete it if the paintable is gone.
+    fn release_texture(
+        pool: Weak<GlTarget>,
+        painter: &GlPainter,
+        texture: glow::Texture,
+        size: [u32; 2],
+    ) {
+        match pool.upgrade() {
+            Some(gl_target) => gl_target.free_textures.borrow_mut().push((texture, size)),
+            None => delete_textures(painter, vec![(texture, size)]),
+        }
+    }
+
+    fn delete_textures(painter: &GlPainter, textures: Vec<(glow::Texture, [u32; 2])>) {
+        if textures.is_empty() {
+            return;
+        }
+        painter.context().make_current();
+        let gl = painter.painter.borrow().gl().clone();
+        for (texture, _) in textures {
+            unsafe { gl.delete_texture(texture) };
+        }
+    }
+}
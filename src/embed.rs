@@ -0,0 +1,115 @@
+use crate::EguiArea;
+use egui_glow::glow;
+use gtk::{
+    glib,
+    prelude::{Cast, IsA},
+    subclass::prelude::ObjectSubclassIsExt,
+};
+use std::{cell::RefCell, sync::Arc};
+
+thread_local! {
+    /// The area whose UI function is currently running.
+    static CURRENT_AREA: RefCell<Option<EguiArea>> = const { RefCell::new(None) };
+}
+
+/// A GTK widget placed inside of the egui UI.
+#[derive(Clone, PartialEq)]
+pub(crate) struct EmbeddedChild {
+    pub widget: gtk::Widget,
+    /// Where the widget is allocated, in area coordinates
+    pub rect: egui::Rect,
+    /// The part of the widget that is visible, in area coordinates
+    pub clip_rect: egui::Rect,
+}
+
+/// Extension trait for using GTK widgets and icons inside of an egui [`egui::Ui`].
+pub trait UiExt {
+    /// Reserve space for a GTK widget of the given size and show the widget there.
+    ///
+    /// The widget becomes a child of the [`EguiArea`] that draws this UI.
+    /// It is drawn above the egui content and receives input like any other GTK widget.
+    /// Children that are not placed in a frame are hidden until they are placed again.
+    fn gtk_widget(&mut self, widget: &impl IsA<gtk::Widget>, size: egui::Vec2) -> egui::Response;
+
+    /// Show an icon from the GTK icon theme, see [`EguiArea::icon`].
+    fn gtk_icon(&mut self, name: &str, size: f32) -> egui::Response;
+
+    /// All pointer positions of the current frame, see [`EguiArea::pointer_history`].
+    fn pointer_history(&self) -> Vec<egui::Pos2>;
+
+    /// The OpenGL context of the area that draws this UI, see [`EguiArea::gl`].
+    fn gl(&self) -> Option<Arc<glow::Context>>;
+}
+
+impl UiExt for egui::Ui {
+    fn gtk_widget(&mut self, widget: &impl IsA<gtk::Widget>, size: egui::Vec2) -> egui::Response {
+        let (rect, response) = self.allocate_exact_size(size, egui::Sense::hover());
+
+        CURRENT_AREA.with_borrow(|area| match area {
+            Some(area) => area.imp().place_child(EmbeddedChild {
+                widget: widget.clone().upcast(),
+                rect,
+                clip_rect: self.clip_rect().intersect(rect),
+            }),
+            None => glib::g_warning!(
+                "gtk-egui-area",
+                "Trying to place a GTK widget outside of an EguiArea UI function"
+            ),
+        });
+
+        response
+    }
+
+    fn gtk_icon(&mut self, name: &str, size: f32) -> egui::Response {
+        match current_area() {
+            Some(area) => self.add(area.icon(name, size)),
+            None => {
+                glib::g_warning!(
+                    "gtk-egui-area",
+                    "Trying to show a GTK icon outside of an EguiArea UI function"
+                );
+                self.allocate_exact_size(egui::Vec2::splat(size), egui::Sense::hover())
+                    .1
+            }
+        }
+    }
+
+    fn pointer_history(&self) -> Vec<egui::Pos2> {
+        match current_area() {
+            Some(area) => area.pointer_history(),
+            None => {
+                glib::g_warning!(
+                    "gtk-egui-area",
+                    "Trying to read the pointer history outside of an EguiArea UI function"
+                );
+                Vec::new()
+            }
+        }
+    }
+
+    fn gl(&self) -> Option<Arc<glow::Context>> {
+        match current_area() {
+            Some(area) => area.gl(),
+            None => {
+                glib::g_warning!(
+                    "gtk-egui-area",
+                    "Trying to get the GL context outside of an EguiArea UI function"
+                );
+                None
+            }
+        }
+    }
+}
+
+/// Run `f` with `area` set as the area that GTK widgets are placed in.
+pub(crate) fn with_current_area<R>(area: &EguiArea, f: impl FnOnce() -> R) -> R {
+    let previous = CURRENT_AREA.replace(Some(area.clone()));
+    let result = f();
+    CURRENT_AREA.set(previous);
+    result
+}
+
+/// The area whose UI function is currently running.
+pub(crate) fn current_area() -> Option<EguiArea> {
+    CURRENT_AREA.with_borrow(Clone::clone)
+}
@@ -0,0 +1,143 @@
+use std::time::Duration;
+
+/// The refresh rate that is assumed when the monitor doesn't report one.
+pub(crate) const DEFAULT_REFRESH_RATE: f32 = 60.0;
+
+/// A limit for how often an [`crate::EguiArea`] renders.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FrameRate {
+    /// Render on every frame of the display.
+    #[default]
+    Unlimited,
+    /// Render at most this many frames per second. `0` is the same as [`FrameRate::Unlimited`].
+    Fps(u32),
+    /// Render at a fraction of the refresh rate of the monitor, e.g. `0.5` for every other frame.
+    RefreshRateFraction(f32),
+}
+
+/// Frame rate limits for an [`crate::EguiArea`] that depend on the state of the system.
+///
+/// When several limits apply, the lowest frame rate wins.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameRatePolicy {
+    /// The limit that always applies.
+    pub normal: FrameRate,
+    /// The limit while power saver mode is enabled. Requires the `v2_70` feature.
+    pub power_saver: Option<FrameRate>,
+    /// The limit while the window of the area is in the background.
+    pub backdrop: Option<FrameRate>,
+}
+
+impl FrameRate {
+    /// The shortest time between two frames, for a monitor with `refresh_rate` frames per second.
+    fn min_interval(self, refresh_rate: Option<f32>) -> Option<Duration> {
+        let fps = match self {
+            FrameRate::Unlimited | FrameRate::Fps(0) => return None,
+            FrameRate::Fps(fps) => fps as f32,
+            FrameRate::RefreshRateFraction(fraction) => {
+                refresh_rate.unwrap_or(DEFAULT_REFRESH_RATE) * fraction
+            }
+        };
+        (fps > 0.0).then(|| Duration::from_secs_f32(1.0 / fps))
+    }
+}
+
+impl FrameRatePolicy {
+    /// The shortest time between two frames under the limits that currently apply.
+    pub(crate) fn min_interval(
+        &self,
+        power_saver: bool,
+        backdrop: bool,
+        refresh_rate: Option<f32>,
+    ) -> Option<Duration> {
+        [
+            Some(self.normal),
+            self.power_saver.filter(|_| power_saver),
+            self.backdrop.filter(|_| backdrop),
+        ]
+        .into_iter()
+        .flatten()
+        .filter_map(|frame_rate| frame_rate.min_interval(refresh_rate))
+        .max()
+    }
+}
+
+/// Whether the system is in power saver mode.
+pub(crate) fn power_saver_enabled() -> bool {
+    #[cfg(feature = "v2_70")]
+    {
+        use gtk::gio::prelude::PowerProfileMonitorExt;
+        gtk::gio::PowerProfileMonitor::get_default().is_power_saver_enabled()
+    }
+    #[cfg(not(feature = "v2_70"))]
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FrameRate, FrameRatePolicy, DEFAULT_REFRESH_RATE};
+    use std::time::Duration;
+
+    #[test]
+    fn fps_limit() {
+        assert_eq!(
+            FrameRate::Fps(50).min_interval(Some(144.0)),
+            Some(Duration::from_secs_f32(1.0 / 50.0))
+        );
+        assert_eq!(FrameRate::Fps(0).min_interval(Some(144.0)), None);
+        assert_eq!(FrameRate::Unlimited.min_interval(Some(144.0)), None);
+    }
+
+    #[test]
+    fn refresh_rate_fraction() {
+        assert_eq!(
+            FrameRate::RefreshRateFraction(0.5).min_interval(Some(120.0)),
+            Some(Duration::from_secs_f32(1.0 / 60.0))
+        );
+        assert_eq!(
+            FrameRate::RefreshRateFraction(1.0).min_interval(None),
+            Some(Duration::from_secs_f32(1.0 / DEFAULT_REFRESH_RATE))
+        );
+        assert_eq!(
+            FrameRate::RefreshRateFraction(0.0).min_interval(Some(60.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn policy_applies_limits_of_current_state() {
+        let policy = FrameRatePolicy {
+            normal: FrameRate::Unlimited,
+            power_saver: Some(FrameRate::Fps(30)),
+            backdrop: Some(FrameRate::Fps(10)),
+        };
+
+        assert_eq!(policy.min_interval(false, false, Some(60.0)), None);
+        assert_eq!(
+            policy.min_interval(true, false, Some(60.0)),
+            Some(Duration::from_secs_f32(1.0 / 30.0))
+        );
+        assert_eq!(
+            policy.min_interval(false, true, Some(60.0)),
+            Some(Duration::from_secs_f32(1.0 / 10.0))
+        );
+    }
+
+    #[test]
+    fn policy_uses_lowest_frame_rate() {
+        let policy = FrameRatePolicy {
+            normal: FrameRate::Fps(20),
+            power_saver: Some(FrameRate::Fps(30)),
+            backdrop: Some(FrameRate::Fps(0)),
+        };
+
+        assert_eq!(
+            policy.min_interval(true, true, Some(60.0)),
+            Some(Duration::from_secs_f32(1.0 / 20.0))
+        );
+        assert_eq!(
+            FrameRatePolicy::default().min_interval(true, true, None),
+            None
+        );
+    }
+}
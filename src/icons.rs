@@ -0,0 +1,127 @@
+use crate::{paintable, EguiArea};
+use gtk::{
+    glib::{self, clone},
+    prelude::{Cast, GLAreaExt, ObjectExt, WidgetExt},
+    subclass::prelude::ObjectSubclassIsExt,
+};
+use std::collections::HashMap;
+
+/// Icons from the GTK icon theme, rendered into egui textures.
+#[derive(Default)]
+pub(crate) struct IconCache {
+    icons: HashMap<IconKey, CachedIcon>,
+    theme: Option<(gtk::IconTheme, glib::SignalHandlerId)>,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct IconKey {
+    name: String,
+    /// The bits of the size in points
+    size: u32,
+    scale: i32,
+}
+
+struct CachedIcon {
+    texture: egui::TextureHandle,
+    symbolic: bool,
+}
+
+impl IconCache {
+    /// An image of the icon `name` that is `size` points large, rendering the icon if it isn't cached yet.
+    pub fn icon(&mut self, area: &EguiArea, name: &str, size: f32) -> egui::Image<'static> {
+        let scale = crate::scale_factor(area.upcast_ref()).ceil() as i32;
+        let key = IconKey {
+            name: name.to_owned(),
+            size: size.to_bits(),
+            scale,
+        };
+
+        if !self.icons.contains_key(&key) {
+            let icon = self.render(area, name, size, scale);
+            self.icons.insert(key.clone(), icon);
+        }
+        let icon = &self.icons[&key];
+
+        let image = egui::Image::new(egui::load::SizedTexture::new(
+            icon.texture.id(),
+            egui::Vec2::splat(size),
+        ));
+        if icon.symbolic {
+            image.tint(area.egui_ctx().style().visuals.text_color())
+        } else {
+            image
+        }
+    }
+
+    /// Remove all icons, returning the number of bytes that their textures used.
+    /// Icons are rendered again when they are shown the next time.
+    pub fn evict(&mut self) -> usize {
+        self.icons
+            .drain()
+            .map(|(_, icon)| icon.texture.byte_size())
+            .sum()
+    }
+
+    /// Remove all icons and stop following the icon theme.
+    pub fn clear(&mut self) {
+        self.icons.clear();
+        if let Some((theme, handler)) = self.theme.take() {
+            theme.disconnect(handler);
+        }
+    }
+
+    fn render(&mut self, area: &EguiArea, name: &str, size: f32, scale: i32) -> CachedIcon {
+        let icon = self.theme(area).lookup_icon(
+            name,
+            &[],
+            size.round() as i32,
+            scale,
+            area.direction(),
+            gtk::IconLookupFlags::empty(),
+        );
+
+        let mut image = paintable::render(
+            Some(area),
+            icon.upcast_ref(),
+            egui::Vec2::splat(size),
+            scale as f32,
+        )
+        .map(|texture| paintable::download(&texture))
+        .unwrap_or_else(|| egui::ColorImage::new([1, 1], egui::Color32::TRANSPARENT));
+
+        // Symbolic icons are stored as white shapes, so they can be tinted with the text color when drawn
+        let symbolic = icon.is_symbolic();
+        if symbolic {
+            for pixel in &mut image.pixels {
+                let alpha = pixel.a();
+                *pixel = egui::Color32::from_rgba_premultiplied(alpha, alpha, alpha, alpha);
+            }
+        }
+
+        CachedIcon {
+            texture: area.egui_ctx().load_texture(
+                format!("gtk-icon-{name}"),
+                image,
+                egui::TextureOptions::LINEAR,
+            ),
+            symbolic,
+        }
+    }
+
+    /// The icon theme of the display of the area. Cached icons are rendered again when the theme changes.
+    fn theme(&mut self, area: &EguiArea) -> gtk::IconTheme {
+        let (theme, _) = self.theme.get_or_insert_with(|| {
+            let theme = gtk::IconTheme::for_display(&area.display());
+            let handler = theme.connect_changed(clone!(
+                #[weak]
+                area,
+                move |_| {
+                    area.imp().icons.borrow_mut().icons.clear();
+                    area.queue_render();
+                }
+            ));
+            (theme, handler)
+        });
+        theme.clone()
+    }
+}
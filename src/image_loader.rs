@@ -0,0 +1,203 @@
+use egui::{
+    load::{BytesPoll, ImageLoadResult, ImagePoll, LoadError, SizeHint},
+    mutex::Mutex,
+    ColorImage,
+};
+use gtk::{
+    gdk_pixbuf::{self, Pixbuf, PixbufLoader},
+    gio,
+    prelude::PixbufLoaderExt,
+};
+use std::{collections::HashMap, sync::Arc, task::Poll};
+
+type Entry = Poll<Result<Arc<ColorImage>, LoadError>>;
+
+/// An [`egui::load::ImageLoader`] that decodes images with gdk-pixbuf,
+/// supporting all formats that GTK can load (PNG, JPEG, WebP, SVG and others depending on the installed loaders).
+///
+/// Images are decoded on the gio thread pool. SVG images are rasterized at the requested size in physical pixels.
+/// The image bytes are loaded with the installed [`egui::load::BytesLoader`]s.
+#[derive(Default)]
+pub struct GtkImageLoader {
+    cache: Arc<Mutex<HashMap<CacheKey, Entry>>>,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    uri: String,
+    /// The size that an SVG image is rasterized at, as a size hint and the bits of the pixels per point
+    svg_size: Option<(SizeHint, u32)>,
+}
+
+impl GtkImageLoader {
+    pub const ID: &'static str = egui::generate_loader_id!(GtkImageLoader);
+
+    /// Install the loader on `ctx`, unless it's already installed.
+    pub fn install(ctx: &egui::Context) {
+        if !ctx.is_loader_installed(Self::ID) {
+            ctx.add_image_loader(Arc::new(Self::default()));
+        }
+    }
+}
+
+impl egui::load::ImageLoader for GtkImageLoader {
+    fn id(&self) -> &str {
+        Self::ID
+    }
+
+    fn load(&self, ctx: &egui::Context, uri: &str, size_hint: SizeHint) -> ImageLoadResult {
+        let (bytes, mime) = match ctx.try_load_bytes(uri)? {
+            BytesPoll::Ready { bytes, mime, .. } => (bytes, mime),
+            BytesPoll::Pending { size } => return Ok(ImagePoll::Pending { size }),
+        };
+
+        let svg_size = is_svg(uri, mime.as_deref()).then(|| {
+            // Images shown at their original size take their size in points from the image,
+            // so only images with an explicit size can be rasterized at the physical resolution
+            let pixels_per_point = match size_hint {
+                SizeHint::Scale(_) => 1.0,
+                _ => ctx.pixels_per_point(),
+            };
+            (size_hint, pixels_per_point.to_bits())
+        });
+        let key = CacheKey {
+            uri: uri.to_owned(),
+            svg_size,
+        };
+
+        let mut cache = self.cache.lock();
+        if let Some(entry) = cache.get(&key) {
+            return match entry {
+                Poll::Ready(Ok(image)) => Ok(ImagePoll::Ready {
+                    image: image.clone(),
+                }),
+                Poll::Ready(Err(err)) => Err(err.clone()),
+                Poll::Pending => Ok(ImagePoll::Pending { size: None }),
+            };
+        }
+        cache.insert(key.clone(), Poll::Pending);
+        drop(cache);
+
+        let cache = self.cache.clone();
+        let ctx = ctx.clone();
+        // The task runs on its own, the result is delivered through the cache instead of the join handle
+        drop(gio::spawn_blocking(move || {
+            let result = decode(&bytes, mime.as_deref(), key.svg_size).map(Arc::new);
+            // The entry is gone if the image was forgotten while decoding
+            if let Some(entry) = cache.lock().get_mut(&key) {
+                *entry = Poll::Ready(result);
+            }
+            ctx.request_repaint();
+        }));
+
+        Ok(ImagePoll::Pending { size: None })
+    }
+
+    fn forget(&self, uri: &str) {
+        self.cache.lock().retain(|key, _| key.uri != uri);
+    }
+
+    fn forget_all(&self) {
+        self.cache.lock().clear();
+    }
+
+    fn byte_size(&self) -> usize {
+        self.cache
+            .lock()
+            .values()
+            .map(|entry| match entry {
+                Poll::Ready(Ok(image)) => image.pixels.len() * std::mem::size_of::<egui::Color32>(),
+                Poll::Ready(Err(err)) => err.to_string().len(),
+                Poll::Pending => 0,
+            })
+            .sum()
+    }
+}
+
+fn is_svg(uri: &str, mime: Option<&str>) -> bool {
+    match mime {
+        Some(mime) => mime.starts_with("image/svg"),
+        None => uri.ends_with(".svg") || uri.ends_with(".svgz"),
+    }
+}
+
+fn decode(
+    bytes: &[u8],
+    mime: Option<&str>,
+    svg_size: Option<(SizeHint, u32)>,
+) -> Result<ColorImage, LoadError> {
+    let loader = match mime {
+        Some(mime) if mime.starts_with("image/") => {
+            PixbufLoader::with_mime_type(mime).map_err(|_| LoadError::NotSupported)?
+        }
+        _ => PixbufLoader::new(),
+    };
+
+    if let Some((size_hint, pixels_per_point)) = svg_size {
+        let pixels_per_point = f32::from_bits(pixels_per_point);
+        loader.connect_size_prepared(move |loader, width, height| {
+            let [width, height] = scaled_size(size_hint, pixels_per_point, [width, height]);
+            loader.set_size(width, height);
+        });
+    }
+
+    // The loader must always be closed, even if writing failed
+    let written = loader.write(bytes);
+    let closed = loader.close();
+    written.and(closed).map_err(|err| {
+        if err.matches(gdk_pixbuf::PixbufError::UnknownType) {
+            LoadError::NotSupported
+        } else {
+            LoadError::Loading(err.to_string())
+        }
+    })?;
+
+    let pixbuf = loader
+        .pixbuf()
+        .ok_or_else(|| LoadError::Loading("The image has no pixel data".to_owned()))?;
+    Ok(pixbuf_to_image(&pixbuf))
+}
+
+/// The size in pixels to rasterize an image of the given original size at.
+fn scaled_size(size_hint: SizeHint, pixels_per_point: f32, [width, height]: [i32; 2]) -> [i32; 2] {
+    let (width, height) = (width.max(1) as f32, height.max(1) as f32);
+    let scale = match size_hint {
+        SizeHint::Scale(scale) => scale.into_inner(),
+        SizeHint::Width(hint_width) => hint_width as f32 / width,
+        SizeHint::Height(hint_height) => hint_height as f32 / height,
+        SizeHint::Size(hint_width, hint_height) => {
+            (hint_width as f32 / width).min(hint_height as f32 / height)
+        }
+    } * pixels_per_point;
+
+    [
+        (width * scale).round().max(1.0) as i32,
+        (height * scale).round().max(1.0) as i32,
+    ]
+}
+
+fn pixbuf_to_image(pixbuf: &Pixbuf) -> ColorImage {
+    let width = pixbuf.width() as usize;
+    let height = pixbuf.height() as usize;
+    let n_channels = pixbuf.n_channels() as usize;
+    let rowstride = pixbuf.rowstride() as usize;
+    let bytes = pixbuf.read_pixel_bytes();
+
+    let mut pixels = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let offset = y * rowstride + x * n_channels;
+            let pixel = &bytes[offset..offset + n_channels];
+            pixels.push(match pixel {
+                [r, g, b, a] => egui::Color32::from_rgba_unmultiplied(*r, *g, *b, *a),
+                [r, g, b] => egui::Color32::from_rgb(*r, *g, *b),
+                _ => egui::Color32::TRANSPARENT,
+            });
+        }
+    }
+
+    ColorImage {
+        size: [width, height],
+        pixels,
+    }
+}
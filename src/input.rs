@@ -0,0 +1,411 @@
+use gtk::{
+    gio,
+    glib::{self, translate::IntoGlib},
+    prelude::{EventControllerExt, WidgetExt},
+};
+use std::{cell::Cell, rc::Rc};
+
+/// Receives the egui events produced by the input controllers of a widget.
+pub(crate) type EventSink = Rc<dyn Fn(egui::Event)>;
+
+/// Add input controllers to `widget` that translate GTK input into egui events.
+pub(crate) fn register_controllers(widget: &gtk::Widget, push_event: EventSink) {
+    let current_modifiers = Rc::new(Cell::new(egui::Modifiers::default()));
+
+    let gesture_click = gtk::GestureClick::new();
+    gesture_click.connect_pressed(glib::clone!(
+        #[strong]
+        current_modifiers,
+        #[strong]
+        push_event,
+        move |gesture, _num, x, y| {
+            if let Some(widget) = gesture.widget() {
+                widget.grab_focus();
+            }
+
+            push_event(egui::Event::PointerButton {
+                pos: egui::pos2(x as f32, y as f32),
+                button: egui::PointerButton::Primary,
+                pressed: true,
+                modifiers: current_modifiers.get(),
+            });
+        }
+    ));
+    gesture_click.connect_released(glib::clone!(
+        #[strong]
+        current_modifiers,
+        #[strong]
+        push_event,
+        move |_gesture, _num, x, y| {
+            push_event(egui::Event::PointerButton {
+                pos: egui::pos2(x as f32, y as f32),
+                button: egui::PointerButton::Primary,
+                pressed: false,
+                modifiers: current_modifiers.get(),
+            });
+        }
+    ));
+
+    let event_controller_motion = gtk::EventControllerMotion::new();
+    event_controller_motion.connect_motion(glib::clone!(
+        #[strong]
+        push_event,
+        move |motion, x, y| {
+            // GDK merges motion events that arrive within a frame, keeping the merged positions in the event history
+            if let Some(event) = motion.current_event() {
+                for pos in motion_history(&event, x, y) {
+                    push_event(egui::Event::PointerMoved(pos));
+                }
+            }
+            push_event(egui::Event::PointerMoved(egui::pos2(x as f32, y as f32)));
+        }
+    ));
+    event_controller_motion.connect_leave(glib::clone!(
+        #[strong]
+        push_event,
+        move |_motion| {
+            push_event(egui::Event::PointerGone);
+        }
+    ));
+
+    let event_controller_scroll = gtk::EventControllerScroll::new(
+        gtk::EventControllerScrollFlags::BOTH_AXES | gtk::EventControllerScrollFlags::DISCRETE,
+    );
+    event_controller_scroll.connect_scroll(glib::clone!(
+        #[strong]
+        current_modifiers,
+        #[strong]
+        push_event,
+        move |_scroll, x, y| {
+            push_event(egui::Event::MouseWheel {
+                unit: egui::MouseWheelUnit::Line,
+                delta: egui::Vec2::new(-x as f32, -y as f32),
+                modifiers: current_modifiers.get(),
+            });
+            glib::Propagation::Proceed
+        }
+    ));
+
+    let event_controller_key = gtk::EventControllerKey::new();
+    event_controller_key.connect_key_pressed(glib::clone!(
+        #[strong]
+        push_event,
+        move |controller, key, _code, modifiers| {
+            if modifiers.is_empty() || modifiers.contains(gtk::gdk::ModifierType::SHIFT_MASK) {
+                if let Some(char) = key.to_unicode() {
+                    if !char.is_control() {
+                        push_event(egui::Event::Text(char.into()));
+                    }
+                }
+            }
+
+            if let Some(key) = gdk_to_egui_key(key) {
+                let modifiers = gdk_to_egui_modifiers(modifiers);
+
+                if is_copy_command(modifiers, key) {
+                    push_event(egui::Event::Copy);
+                } else if is_cut_command(modifiers, key) {
+                    push_event(egui::Event::Cut);
+                } else if is_paste_command(modifiers, key) {
+                    if let Some(widget) = controller.widget() {
+                        let push_event = push_event.clone();
+                        widget
+                            .clipboard()
+                            .read_text_async(gio::Cancellable::NONE, move |result| {
+                                if let Ok(Some(text)) = result {
+                                    push_event(egui::Event::Paste(text.to_string()));
+                                }
+                            });
+                    }
+                }
+
+                push_event(egui::Event::Key {
+                    key,
+                    physical_key: None,
+                    pressed: true,
+                    repeat: false,
+                    modifiers,
+                });
+            }
+            glib::Propagation::Proceed
+        }
+    ));
+    event_controller_key.connect_key_released(glib::clone!(
+        #[strong]
+        push_event,
+        move |_controller, key, _code, modifiers| {
+            if let Some(key) = gdk_to_egui_key(key) {
+                push_event(egui::Event::Key {
+                    key,
+                    physical_key: None,
+                    pressed: false,
+                    repeat: false,
+                    modifiers: gdk_to_egui_modifiers(modifiers),
+                });
+            }
+        }
+    ));
+    event_controller_key.connect_modifiers(move |_controller, new_modifiers| {
+        current_modifiers.set(gdk_to_egui_modifiers(new_modifiers));
+        glib::Propagation::Proceed
+    });
+
+    widget.add_controller(event_controller_motion);
+    widget.add_controller(gesture_click);
+    widget.add_controller(event_controller_scroll);
+    widget.add_controller(event_controller_key);
+}
+
+/// The pointer positions that GDK merged into a motion event at `x`, `y`, in widget coordinates.
+fn motion_history(event: &gtk::gdk::Event, x: f64, y: f64) -> Vec<egui::Pos2> {
+    let Some((event_x, event_y)) = event.position() else {
+        return Vec::new();
+    };
+    // The history is in surface coordinates, while the controller reports widget coordinates
+    let (offset_x, offset_y) = (event_x - x, event_y - y);
+
+    event
+        .history()
+        .iter()
+        .map(|coord| {
+            let axes = coord.axes();
+            egui::pos2(
+                (axes[gtk::gdk::AxisUse::X.into_glib() as usize] - offset_x) as f32,
+                (axes[gtk::gdk::AxisUse::Y.into_glib() as usize] - offset_y) as f32,
+            )
+        })
+        .collect()
+}
+
+/// The parts of an egui viewport that take pointer input even where it has no interactive widgets.
+///
+/// egui only keeps this for the viewport that ran last, so it's recorded right after a frame.
+#[derive(Default)]
+pub(crate) struct PointerTargets {
+    using_pointer: bool,
+    /// The rects of the interactable areas, such as windows
+    areas: Vec<egui::Rect>,
+}
+
+impl PointerTargets {
+    /// The targets of the viewport that ran last.
+    pub fn of_last_frame(ctx: &egui::Context) -> Self {
+        let layers: Vec<(egui::LayerId, Option<egui::emath::TSTransform>)> = ctx.memory(|memory| {
+            memory
+                .layer_ids()
+                .filter(|layer_id| memory.areas().is_visible(layer_id))
+                .map(|layer_id| (layer_id, memory.layer_transforms.get(&layer_id).copied()))
+                .collect()
+        });
+        let areas = layers
+            .into_iter()
+            .filter_map(|(layer_id, transform)| {
+                let state = egui::AreaState::load(ctx, layer_id.id)?;
+                let rect = state.rect();
+                state
+                    .interactable
+                    .then(|| transform.map_or(rect, |transform| transform * rect))
+            })
+            .collect();
+
+        Self {
+            using_pointer: ctx.is_using_pointer(),
+            areas,
+        }
+    }
+
+    pub fn contains(&self, pos: egui::Pos2) -> bool {
+        self.using_pointer || self.areas.iter().any(|rect| rect.contains(pos))
+    }
+}
+
+/// Merge `event` into the `previous` event if only the latest position or the total delta of both matters,
+/// returning whether it was merged.
+pub(crate) fn coalesce(previous: &mut egui::Event, event: &egui::Event) -> bool {
+    match (previous, event) {
+        (egui::Event::PointerMoved(previous), egui::Event::PointerMoved(pos)) => {
+            *previous = *pos;
+            true
+        }
+        (
+            egui::Event::MouseWheel {
+                unit,
+                delta,
+                modifiers,
+            },
+            egui::Event::MouseWheel {
+                unit: new_unit,
+                delta: new_delta,
+                modifiers: new_modifiers,
+            },
+        ) if unit == new_unit && modifiers == new_modifiers => {
+            *delta += *new_delta;
+            true
+        }
+        _ => false,
+    }
+}
+
+fn gdk_to_egui_key(key: gtk::gdk::Key) -> Option<egui::Key> {
+    use egui::Key as EguiKey;
+    use gtk::gdk::Key;
+    let key = match key {
+        Key::BackSpace => EguiKey::Backspace,
+        Key::Down => EguiKey::ArrowDown,
+        Key::Up => EguiKey::ArrowUp,
+        Key::Left => EguiKey::ArrowLeft,
+        Key::Right => EguiKey::ArrowRight,
+        Key::KP_Enter | Key::ISO_Enter => EguiKey::Enter,
+        Key::space | Key::KP_Space => EguiKey::Space,
+        Key::Page_Up => EguiKey::PageUp,
+        Key::Page_Down => EguiKey::PageDown,
+        Key::colon => EguiKey::Colon,
+        Key::comma => EguiKey::Comma,
+        Key::backslash => EguiKey::Backslash,
+        Key::slash => EguiKey::Slash,
+        Key::vertbar => EguiKey::Pipe,
+        Key::question => EguiKey::Questionmark,
+        Key::bracketleft => EguiKey::OpenBracket,
+        Key::braceright => EguiKey::CloseBracket,
+        Key::grave => EguiKey::Backtick,
+        Key::minus => EguiKey::Minus,
+        Key::period => EguiKey::Period,
+        Key::plus => EguiKey::Plus,
+        Key::equal => EguiKey::Equals,
+        Key::semicolon => EguiKey::Semicolon,
+        Key::singlelowquotemark => EguiKey::Quote,
+        Key::_0 | Key::KP_0 => EguiKey::Num0,
+        Key::_1 | Key::KP_1 => EguiKey::Num1,
+        Key::_2 | Key::KP_2 => EguiKey::Num2,
+        Key::_3 | Key::KP_3 => EguiKey::Num3,
+        Key::_4 | Key::KP_4 => EguiKey::Num4,
+        Key::_5 | Key::KP_5 => EguiKey::Num5,
+        Key::_6 | Key::KP_6 => EguiKey::Num6,
+        Key::_7 | Key::KP_7 => EguiKey::Num7,
+        Key::_8 | Key::KP_8 => EguiKey::Num8,
+        Key::_9 | Key::KP_9 => EguiKey::Num9,
+        _ => return key.name().and_then(|name| egui::Key::from_name(&name)),
+    };
+    Some(key)
+}
+
+fn gdk_to_egui_modifiers(modifiers: gtk::gdk::ModifierType) -> egui::Modifiers {
+    use gtk::gdk::ModifierType;
+    egui::Modifiers {
+        alt: modifiers.contains(ModifierType::ALT_MASK),
+        ctrl: modifiers.contains(ModifierType::CONTROL_MASK),
+        shift: modifiers.contains(ModifierType::SHIFT_MASK),
+        mac_cmd: modifiers.contains(ModifierType::META_MASK),
+        #[cfg(target_os = "macos")]
+        command: modifiers.contains(ModifierType::META_MASK),
+        #[cfg(not(target_os = "macos"))]
+        command: modifiers.contains(ModifierType::CONTROL_MASK),
+    }
+}
+
+fn is_cut_command(modifiers: egui::Modifiers, key: egui::Key) -> bool {
+    key == egui::Key::Cut
+        || (modifiers.command && key == egui::Key::X)
+        || (cfg!(target_os = "windows") && modifiers.shift && key == egui::Key::Delete)
+}
+
+fn is_copy_command(modifiers: egui::Modifiers, key: egui::Key) -> bool {
+    key == egui::Key::Copy
+        || (modifiers.command && key == egui::Key::C)
+        || (cfg!(target_os = "windows") && modifiers.ctrl && key == egui::Key::Insert)
+}
+
+fn is_paste_command(modifiers: egui::Modifiers, key: egui::Key) -> bool {
+    key == egui::Key::Paste
+        || (modifiers.command && key == egui::Key::V)
+        || (cfg!(target_os = "windows") && modifiers.shift && key == egui::Key::Insert)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::coalesce;
+
+    fn wheel(unit: egui::MouseWheelUnit, delta: egui::Vec2, shift: bool) -> egui::Event {
+        egui::Event::MouseWheel {
+            unit,
+            delta,
+            modifiers: egui::Modifiers {
+                shift,
+                ..Default::default()
+            },
+        }
+    }
+
+    fn button(pressed: bool) -> egui::Event {
+        egui::Event::PointerButton {
+            pos: egui::pos2(1.0, 1.0),
+            button: egui::PointerButton::Primary,
+            pressed,
+            modifiers: egui::Modifiers::default(),
+        }
+    }
+
+    #[test]
+    fn pointer_motion_keeps_last_position() {
+        let mut previous = egui::Event::PointerMoved(egui::pos2(1.0, 2.0));
+        assert!(coalesce(
+            &mut previous,
+            &egui::Event::PointerMoved(egui::pos2(3.0, 4.0))
+        ));
+        assert_eq!(previous, egui::Event::PointerMoved(egui::pos2(3.0, 4.0)));
+    }
+
+    #[test]
+    fn wheel_deltas_are_summed() {
+        let mut previous = wheel(egui::MouseWheelUnit::Line, egui::vec2(0.0, 1.0), false);
+        assert!(coalesce(
+            &mut previous,
+            &wheel(egui::MouseWheelUnit::Line, egui::vec2(1.0, 2.0), false)
+        ));
+        assert_eq!(
+            previous,
+            wheel(egui::MouseWheelUnit::Line, egui::vec2(1.0, 3.0), false)
+        );
+    }
+
+    #[test]
+    fn different_wheels_are_not_merged() {
+        let original = wheel(egui::MouseWheelUnit::Line, egui::vec2(0.0, 1.0), false);
+
+        let mut previous = original.clone();
+        assert!(!coalesce(
+            &mut previous,
+            &wheel(egui::MouseWheelUnit::Point, egui::vec2(0.0, 1.0), false)
+        ));
+        assert!(!coalesce(
+            &mut previous,
+            &wheel(egui::MouseWheelUnit::Line, egui::vec2(0.0, 1.0), true)
+        ));
+        assert_eq!(previous, original);
+    }
+
+    #[test]
+    fn keys_and_buttons_are_not_merged() {
+        let key = egui::Event::Key {
+            key: egui::Key::A,
+            physical_key: None,
+            pressed: true,
+            repeat: false,
+            modifiers: egui::Modifiers::default(),
+        };
+
+        let mut previous = key.clone();
+        assert!(!coalesce(&mut previous, &key));
+        let mut previous = button(true);
+        assert!(!coalesce(&mut previous, &button(false)));
+        assert!(!coalesce(&mut previous, &button(true)));
+        assert_eq!(previous, button(true));
+
+        // Motion after a button press must still be delivered after it
+        let mut previous = button(true);
+        assert!(!coalesce(
+            &mut previous,
+            &egui::Event::PointerMoved(egui::pos2(3.0, 4.0))
+        ));
+    }
+}
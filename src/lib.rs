@@ -1,18 +1,63 @@
 #![doc = include_str!("../README.md")]
 
+pub use bytes_loader::{load_font_data, GioBytesLoader};
+pub use capture::{image_to_texture, save_png};
+pub use custom_paint::paint_callback;
 pub use egui;
+pub use egui_glow;
+pub use egui_paintable::EguiPaintable;
+pub use embed::UiExt;
+pub use frame_rate::{FrameRate, FrameRatePolicy};
+pub use image_loader::GtkImageLoader;
+pub use memory::LowMemoryPolicy;
+pub use paintable::PaintableTexture;
+pub use painter_options::PainterOptions;
 
+mod bytes_loader;
+mod capture;
+mod clip_bin;
+mod custom_paint;
+mod egui_paintable;
+mod embed;
+mod frame_rate;
+mod icons;
+mod image_loader;
+mod input;
+mod memory;
+mod paintable;
+mod painter_options;
+mod popups;
+mod shared;
+mod sizing;
+mod software;
+mod surface;
+mod textures;
+mod viewports;
+
+use egui_glow::glow;
 use gtk::{
+    gdk,
     glib::{self, Object},
+    prelude::{Cast, GLAreaExt, GLContextExt, IsA, NativeExt, ObjectExt, SurfaceExt, WidgetExt},
     subclass::prelude::ObjectSubclassIsExt,
 };
-use std::{ptr, sync::OnceLock, time::Duration};
+use shared::SharedContext;
+use std::{
+    ptr,
+    rc::Rc,
+    sync::{Arc, OnceLock},
+};
 
 glib::wrapper! {
     /// Widget for drawing an [`egui`] UI. Inherits from [`gtk::GLArea`].
+    ///
+    /// The area implements [`gtk::Scrollable`], so it can be placed directly inside of a [`gtk::ScrolledWindow`].
+    /// The scrollable content size is the natural size reported by the function set with [`EguiArea::set_size_fn`],
+    /// or without one, the size of the content when it's laid out at the width of the area.
+    /// The adjustment values offset the root egui content.
     pub struct EguiArea(ObjectSubclass<imp::EguiArea>)
         @extends gtk::GLArea, gtk::Widget,
-        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget;
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Scrollable;
 }
 
 impl EguiArea {
@@ -23,6 +68,33 @@ impl EguiArea {
         area
     }
 
+    /// Construct a new [`EguiArea`] that draws with an existing egui context, e.g. the context of another area.
+    ///
+    /// Areas with the same context share fonts, styles, memory and textures,
+    /// and areas on the same display also share the GL context that the textures are uploaded to.
+    /// Each area runs its UI in its own viewport, so windows, focus and input of different areas don't collide,
+    /// and [`egui::Context::viewport_id`] tells the areas apart in a shared UI function.
+    ///
+    /// The [`PainterOptions`] of the first area that is realized on a display configure the shared GL context.
+    pub fn with_context(ui: impl Fn(&egui::Context) + 'static, ctx: egui::Context) -> Self {
+        let area: Self = Object::builder().build();
+        let attached = area.imp().shared.set(SharedContext::attach(ctx)).is_ok();
+        assert!(attached, "a new area already has an egui context");
+        area.set_ui(ui);
+        area
+    }
+
+    /// Construct a new [`EguiArea`], returning an error if OpenGL can't be loaded instead of falling back to software rendering.
+    ///
+    /// Errors that depend on the GL context only show up once the area is realized.
+    /// They are reported with [`EguiArea::connect_render_error`], while the area keeps running with software rendering.
+    pub fn try_new(ui: impl Fn(&egui::Context) + 'static) -> Result<Self, glib::Error> {
+        if !software::is_forced() {
+            init_epoxy()?;
+        }
+        Ok(Self::new(ui))
+    }
+
     /// Construct a new [`EguiArea`] with the provided egui UI function and an FPS limit.
     pub fn with_max_fps(run_ui: impl Fn(&egui::Context) + 'static, max_fps: u32) -> Self {
         let area = Self::new(run_ui);
@@ -30,25 +102,232 @@ impl EguiArea {
         area
     }
 
-    /// Set the maximum FPS for drawing the egui UI.
+    /// Set the maximum FPS for drawing the egui UI. `0` removes the limit.
     ///
     /// This can be useful for reducing CPU usage when you don't need to rerender the UI on every display refresh.
+    /// It sets the normal limit of the [`FrameRatePolicy`].
     pub fn set_max_fps(&self, max_fps: u32) {
-        self.imp()
-            .min_render_interval
-            .set(Some(Duration::from_micros(
-                ((1000.0 / max_fps as f64) * 1000.0) as u64,
-            )));
+        let mut policy = self.imp().frame_rate_policy.get();
+        policy.normal = FrameRate::Fps(max_fps);
+        self.set_frame_rate_policy(policy);
+    }
+
+    /// Set the frame rate limits, e.g. to render less often in power saver mode or while the window is in the background.
+    pub fn set_frame_rate_policy(&self, policy: FrameRatePolicy) {
+        self.imp().frame_rate_policy.set(policy);
+    }
+
+    /// Connect to the `render-error` signal, which is emitted when the area can't render with OpenGL,
+    /// e.g. because no GL context could be created or the egui shaders failed to compile.
+    ///
+    /// The error is also set as the [`gtk::GLArea`] error. The area falls back to software rendering afterwards.
+    pub fn connect_render_error<F: Fn(&Self, &glib::Error) + 'static>(
+        &self,
+        f: F,
+    ) -> glib::SignalHandlerId {
+        self.connect_local("render-error", false, move |values| {
+            let area = values[0].get::<Self>().unwrap();
+            let err = values[1].get::<glib::Error>().unwrap();
+            f(&area, &err);
+            None
+        })
+    }
+
+    /// Pause rendering while the window of the area is in the background, i.e. not focused.
+    ///
+    /// Rendering always pauses while the area is unmapped or its window is minimized.
+    /// Pausing in the background as well saves power for UIs that don't need to update while the user looks elsewhere.
+    pub fn set_pause_in_backdrop(&self, pause: bool) {
+        self.imp().pause_in_backdrop.set(pause);
+        self.imp().update_render_loop();
+    }
+
+    /// Set what the area frees when the system warns about low memory, see [`gtk::gio::MemoryMonitor`].
+    pub fn set_low_memory_policy(&self, policy: LowMemoryPolicy) {
+        self.imp().low_memory_policy.set(policy);
+    }
+
+    /// Connect to the `memory-freed` signal, which is emitted with the number of freed bytes
+    /// after the area freed memory because of a low memory warning.
+    ///
+    /// Memory that is only freed with the next frame, like the old font atlas, isn't counted.
+    pub fn connect_memory_freed<F: Fn(&Self, u64) + 'static>(&self, f: F) -> glib::SignalHandlerId {
+        self.connect_local("memory-freed", false, move |values| {
+            let area = values[0].get::<Self>().unwrap();
+            let bytes = values[1].get::<u64>().unwrap();
+            f(&area, bytes);
+            None
+        })
+    }
+
+    /// Merge consecutive pointer motion and scroll events before they are passed to egui. Enabled by default.
+    ///
+    /// Only the last pointer position and the sum of the scroll deltas between two frames reach egui,
+    /// while button presses and releases keep their order. Apps that need every pointer sample,
+    /// like drawing apps, can disable merging or read the samples with [`EguiArea::pointer_history`].
+    pub fn set_coalesce_input(&self, coalesce: bool) {
+        self.imp().coalesce_input.set(coalesce);
+    }
+
+    /// All pointer positions that were received for the current frame, in points and in order,
+    /// including positions that were merged by GDK or [`EguiArea::set_coalesce_input`].
+    ///
+    /// Inside of the UI function, [`UiExt::pointer_history`] returns the same positions.
+    pub fn pointer_history(&self) -> Vec<egui::Pos2> {
+        self.imp().pointer_history.borrow().clone()
     }
 
     /// Set a new egui UI function.
+    ///
+    /// Without a size function (see [`EguiArea::set_size_fn`]), the UI function also runs without input
+    /// when GTK measures or allocates the area, to lay out the content in a different size.
+    /// Those frames run in a separate viewport and aren't drawn.
     pub fn set_ui(&self, ui: impl Fn(&egui::Context) + 'static) {
         *self.imp().run_ui.borrow_mut() = Some(Box::new(ui));
     }
 
+    /// Set a function that reports the minimum and natural size of the egui content, in points.
+    ///
+    /// The sizes are used when GTK measures the widget. Without a size function,
+    /// the minimum size is zero and the natural size is measured by laying out the UI in an unconstrained space,
+    /// leaving out panels and other widgets that fill all of it.
+    pub fn set_size_fn(
+        &self,
+        size_fn: impl Fn(&egui::Context) -> (egui::Vec2, egui::Vec2) + 'static,
+    ) {
+        *self.imp().size_fn.borrow_mut() = Some(Box::new(size_fn));
+        self.imp().content_size.set(None);
+        self.imp().last_measurement.set(None);
+        self.queue_resize();
+    }
+
+    /// Set the options for the OpenGL context and the egui painter, e.g. to only allow OpenGL ES.
+    ///
+    /// The options are used the next time the area is realized, so they should be set before it's shown.
+    pub fn set_painter_options(&self, options: PainterOptions) {
+        options.apply(self.upcast_ref());
+        *self.imp().painter_options.borrow_mut() = options;
+    }
+
+    /// Set the color used to clear the area before drawing the egui UI.
+    ///
+    /// `None` (the default) uses the `window_fill` color of the current egui style.
+    /// A color that is not fully opaque lets GTK blend the area over whatever is drawn below it.
+    /// Like all egui colors, the color is premultiplied, which is also what [`gtk::GLArea`] expects.
+    pub fn set_clear_color(&self, color: Option<egui::Color32>) {
+        self.imp().clear_color.set(color);
+        self.queue_render();
+    }
+
+    /// Make the area background fully transparent.
+    ///
+    /// This is useful for drawing egui as a HUD on top of other widgets, e.g. inside of a [`gtk::Overlay`].
+    pub fn set_transparent(&self, transparent: bool) {
+        self.set_clear_color(transparent.then_some(egui::Color32::TRANSPARENT));
+    }
+
+    /// Let pointer input pass through the area where egui has nothing under the pointer.
+    ///
+    /// When enabled, the area only picks pointer events over egui windows, areas and interactive widgets,
+    /// so clicks elsewhere reach the GTK widgets below it.
+    pub fn set_input_passthrough(&self, passthrough: bool) {
+        self.imp().input_passthrough.set(passthrough);
+    }
+
+    /// Show egui popups, menus and tooltips that don't fit inside of the area in separate popovers.
+    ///
+    /// When enabled, egui lays out popups relative to the whole window instead of only the area.
+    /// Note that this also lets egui windows be moved outside of the area,
+    /// so windows should be kept inside with [`egui::Window::constrain_to`] and [`egui::Context::available_rect`].
+    pub fn set_external_popups(&self, external_popups: bool) {
+        self.imp().external_popups.set(external_popups);
+    }
+
+    /// The OpenGL context of the area, available while the area is realized and rendered with OpenGL.
+    /// Areas created with [`EguiArea::with_context`] on the same display have the same GL context.
+    ///
+    /// This can be used for creating GL resources used by [`paint_callback`]s and render hooks.
+    /// UI functions can access it with [`UiExt::gl`].
+    pub fn gl(&self) -> Option<Arc<glow::Context>> {
+        self.imp()
+            .painter
+            .borrow()
+            .as_ref()
+            .map(|painter| painter.painter.borrow().gl().clone())
+    }
+
+    /// Set a function that draws with OpenGL before the egui UI, e.g. a scene shown behind the UI.
+    ///
+    /// The function runs on every render after the area is cleared, with the GL viewport covering the whole area.
+    pub fn set_pre_render(
+        &self,
+        pre_render: impl Fn(&glow::Context, &egui::PaintCallbackInfo) + 'static,
+    ) {
+        *self.imp().pre_render.borrow_mut() = Some(Box::new(pre_render));
+        self.queue_render();
+    }
+
+    /// Set a function that draws with OpenGL after the egui UI, e.g. an overlay shown above the UI.
+    pub fn set_post_render(
+        &self,
+        post_render: impl Fn(&glow::Context, &egui::PaintCallbackInfo) + 'static,
+    ) {
+        *self.imp().post_render.borrow_mut() = Some(Box::new(post_render));
+        self.queue_render();
+    }
+
+    /// Create an egui texture that shows the contents of a paintable,
+    /// such as a [`gtk::MediaFile`] video, a [`gtk::IconPaintable`] or a [`gdk::Texture`].
+    ///
+    /// The texture is updated when the paintable changes, and is freed when the returned handle is dropped.
+    pub fn paintable_texture(&self, paintable: &impl IsA<gdk::Paintable>) -> PaintableTexture {
+        PaintableTexture::new(self, paintable.clone().upcast())
+    }
+
+    /// Set a function that sets the texture `id` again, e.g. with [`egui::TextureHandle::set`].
+    ///
+    /// The area keeps a copy of every texture for uploading it to new GL contexts and popup or viewport windows.
+    /// With a reload function, that copy is dropped on low memory warnings (see [`LowMemoryPolicy::evict_textures`])
+    /// and the function is called once the texture has to be uploaded again.
+    pub fn set_texture_reload(&self, id: egui::TextureId, reload: impl Fn() + 'static) {
+        self.imp()
+            .textures()
+            .borrow_mut()
+            .set_reload(id, Rc::new(reload));
+    }
+
+    /// An image of an icon from the GTK icon theme, `size` points large.
+    ///
+    /// Icons are rendered for the scale of the display and cached until the icon theme changes.
+    /// Symbolic icons are tinted with the text color of the egui style.
+    pub fn icon(&self, name: &str, size: f32) -> egui::Image<'static> {
+        self.imp().icons.borrow_mut().icon(self, name, size)
+    }
+
+    /// Install a [`GtkImageLoader`] on the egui context, so images are decoded with GTK.
+    pub fn install_image_loader(&self) {
+        GtkImageLoader::install(self.egui_ctx());
+    }
+
+    /// Capture the contents of the area on its next render.
+    ///
+    /// Returns `None` if the area is destroyed before it is rendered again.
+    /// The image can be converted with [`image_to_texture`] or saved with [`save_png`].
+    pub async fn capture(&self) -> Option<Arc<egui::ColorImage>> {
+        let (sender, receiver) = futures_channel::oneshot::channel();
+        self.imp().captures.borrow_mut().push(sender);
+        self.queue_render();
+        receiver.await.ok()
+    }
+
+    /// The number of frames that were not rendered because they looked the same as the frame before.
+    pub fn skipped_frames(&self) -> u64 {
+        self.imp().skipped_frames.get()
+    }
+
     /// Access the inner [`egui::Context`].
     pub fn egui_ctx(&self) -> &egui::Context {
-        &self.imp().egui_ctx
+        self.imp().egui_ctx()
     }
 }
 
@@ -59,37 +338,114 @@ impl Default for EguiArea {
 }
 
 mod imp {
-    use super::init_epoxy;
-    use egui_glow::glow;
+    use super::{LowMemoryPolicy, PainterOptions};
+    use crate::{
+        clip_bin::ClipBin,
+        custom_paint::{self, DynRenderHook},
+        embed::{self, EmbeddedChild},
+        frame_rate::{self, FrameRatePolicy},
+        icons::IconCache,
+        input, memory,
+        popups::{self, Popups},
+        shared::{GlPainter, SharedContext},
+        sizing,
+        software::{self, SoftwarePainter},
+        textures::TextureStore,
+        viewports::{self, Viewports},
+    };
+    use egui_glow::glow::{self, HasContext};
+    use futures_channel::oneshot;
     use glib::clone;
     use gtk::{
-        gdk::GLContext,
-        gio, glib,
-        prelude::{Cast, GLAreaExt, NativeExt, SurfaceExt, WidgetExt, WidgetExtManual},
+        gdk::{self, GLContext},
+        gio,
+        glib::{self, subclass::Signal},
+        prelude::{
+            AdjustmentExt, Cast, DisplayExt, GLAreaExt, MonitorExt, NativeExt, ObjectExt,
+            StaticType, ToValue, ToplevelExt, WidgetExt, WidgetExtManual,
+        },
         subclass::{
             prelude::{
-                GLAreaImpl, ObjectImpl, ObjectImplExt, ObjectSubclass, ObjectSubclassExt,
-                ObjectSubclassIsExt,
+                GLAreaImpl, GLAreaImplExt, ObjectImpl, ObjectImplExt, ObjectSubclass,
+                ObjectSubclassExt, ObjectSubclassIsExt, ScrollableImpl,
             },
             widget::{WidgetImpl, WidgetImplExt},
         },
     };
     use std::{
-        cell::{Cell, RefCell},
+        cell::{Cell, OnceCell, RefCell},
         rc::Rc,
-        sync::Arc,
-        time::{Duration, Instant},
+        sync::{Arc, OnceLock},
+        time::Duration,
     };
 
     type DynGuiFn = Box<dyn Fn(&egui::Context)>;
 
+    /// The output of a frame that ran in the update phase, waiting to be rendered.
+    struct PreparedFrame {
+        clipped_primitives: Vec<egui::ClippedPrimitive>,
+        content: FrameContent,
+    }
+
+    /// Everything that decides what a frame looks like, apart from the textures.
+    struct FrameContent {
+        shapes: Vec<egui::epaint::ClippedShape>,
+        pixels_per_point: f32,
+        size: [u32; 2],
+    }
+    type DynSizeFn = Box<dyn Fn(&egui::Context) -> (egui::Vec2, egui::Vec2)>;
+
     #[derive(Default)]
     pub struct EguiArea {
-        painter: RefCell<Option<egui_glow::Painter>>,
-        pub(super) egui_ctx: egui::Context,
+        pub(super) painter: RefCell<Option<Rc<GlPainter>>>,
+        software: RefCell<Option<SoftwarePainter>>,
+        pub(crate) painter_options: RefCell<PainterOptions>,
+        pub(super) pre_render: RefCell<Option<DynRenderHook>>,
+        pub(super) post_render: RefCell<Option<DynRenderHook>>,
+        /// Set on first use, unless the area was constructed with an existing context
+        pub(super) shared: OnceCell<Rc<SharedContext>>,
+        /// The viewport that the UI runs in, set on first use
+        area_viewport: Cell<Option<egui::ViewportId>>,
+        pointer_targets: RefCell<input::PointerTargets>,
         input_events: RefCell<Vec<egui::Event>>,
-        pub(super) min_render_interval: Cell<Option<Duration>>,
+        pub(super) coalesce_input: Cell<bool>,
+        /// Every pointer position received since the input was last passed to egui
+        pending_pointer_history: RefCell<Vec<egui::Pos2>>,
+        pub(super) pointer_history: RefCell<Vec<egui::Pos2>>,
+        pub(super) external_popups: Cell<bool>,
+        popups: RefCell<Popups>,
+        pub(crate) icons: RefCell<IconCache>,
+        screenshot_requested: Cell<bool>,
+        pub(super) captures: RefCell<Vec<oneshot::Sender<Arc<egui::ColorImage>>>>,
+        pub(crate) viewports: RefCell<Viewports>,
+        embedded_children: RefCell<Vec<EmbeddedChild>>,
+        placed_children: RefCell<Vec<EmbeddedChild>>,
+        /// The wrappers that clip the embedded widgets, one for every widget that was ever placed
+        clip_bins: RefCell<Vec<ClipBin>>,
+        pub(super) content_size: Cell<Option<(egui::Vec2, egui::Vec2)>>,
+        pub(super) size_fn: RefCell<Option<DynSizeFn>>,
+        /// The content size from the last sizing pass, with the screen size it was laid out in
+        pub(super) last_measurement: Cell<Option<(egui::Vec2, egui::Vec2)>>,
+        layout_fingerprint: Cell<Option<u64>>,
+        hadjustment: RefCell<Option<(gtk::Adjustment, glib::SignalHandlerId)>>,
+        vadjustment: RefCell<Option<(gtk::Adjustment, glib::SignalHandlerId)>>,
+        /// `None` is the default [`gtk::ScrollablePolicy::Minimum`]
+        scroll_policies: Cell<[Option<gtk::ScrollablePolicy>; 2]>,
+        pub(super) clear_color: Cell<Option<egui::Color32>>,
+        pub(super) input_passthrough: Cell<bool>,
+        pub(super) frame_rate_policy: Cell<FrameRatePolicy>,
+        prepared_frame: RefCell<Option<PreparedFrame>>,
+        /// The content of the frame that is currently shown, if it is known
+        drawn_content: RefCell<Option<FrameContent>>,
+        pub(super) skipped_frames: Cell<u64>,
+        tick_callback: RefCell<Option<gtk::TickCallbackId>>,
+        toplevel: RefCell<Option<(gdk::Toplevel, glib::SignalHandlerId)>>,
+        pub(super) pause_in_backdrop: Cell<bool>,
+        pub(crate) low_memory_policy: Cell<LowMemoryPolicy>,
+        memory_monitor: RefCell<Option<(gio::MemoryMonitor, glib::SignalHandlerId)>>,
         pub(super) run_ui: RefCell<Option<DynGuiFn>>,
+        /// Set once the area is disposed, so deferred updates don't bring back what dispose cleared
+        disposed: Cell<bool>,
     }
 
     #[glib::object_subclass]
@@ -97,382 +453,1236 @@ mod imp {
         const NAME: &'static str = "EguiArea";
         type Type = super::EguiArea;
         type ParentType = gtk::GLArea;
+        type Interfaces = (gtk::Scrollable,);
     }
 
     impl ObjectImpl for EguiArea {
+        fn dispose(&self) {
+            self.disposed.set(true);
+            // The UI function and hooks often capture widgets, which may hold references to the area
+            self.run_ui.take();
+            self.size_fn.take();
+            self.pre_render.take();
+            self.post_render.take();
+            self.captures.take();
+
+            // The area is normally unrealized by now, but GL resources must not outlive the object
+            self.painter.take();
+            self.software.take();
+
+            if let Some((monitor, handler)) = self.memory_monitor.take() {
+                monitor.disconnect(handler);
+            }
+            for adjustment in [&self.hadjustment, &self.vadjustment] {
+                if let Some((adjustment, handler)) = adjustment.take() {
+                    adjustment.disconnect(handler);
+                }
+            }
+            self.popups.borrow_mut().clear();
+            self.viewports.borrow_mut().clear();
+            self.icons.borrow_mut().clear();
+            self.clip_bins.take();
+            while let Some(child) = self.obj().first_child() {
+                child.unparent();
+            }
+
+            if let (Some(shared), Some(viewport_id)) =
+                (self.shared.get(), self.area_viewport.take())
+            {
+                shared.release_viewport(viewport_id);
+            }
+        }
+
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: OnceLock<Vec<Signal>> = OnceLock::new();
+            SIGNALS.get_or_init(|| {
+                vec![
+                    Signal::builder("render-error")
+                        .param_types([glib::Error::static_type()])
+                        .build(),
+                    Signal::builder("memory-freed")
+                        .param_types([u64::static_type()])
+                        .build(),
+                ]
+            })
+        }
+
+        fn properties() -> &'static [glib::ParamSpec] {
+            static PROPERTIES: OnceLock<Vec<glib::ParamSpec>> = OnceLock::new();
+            PROPERTIES.get_or_init(|| {
+                vec![
+                    glib::ParamSpecOverride::for_interface::<gtk::Scrollable>("hadjustment"),
+                    glib::ParamSpecOverride::for_interface::<gtk::Scrollable>("vadjustment"),
+                    glib::ParamSpecOverride::for_interface::<gtk::Scrollable>("hscroll-policy"),
+                    glib::ParamSpecOverride::for_interface::<gtk::Scrollable>("vscroll-policy"),
+                ]
+            })
+        }
+
+        fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+            match pspec.name() {
+                "hadjustment" => {
+                    self.set_adjustment(gtk::Orientation::Horizontal, value.get().unwrap())
+                }
+                "vadjustment" => {
+                    self.set_adjustment(gtk::Orientation::Vertical, value.get().unwrap())
+                }
+                "hscroll-policy" => {
+                    let [_, vpolicy] = self.scroll_policies.get();
+                    self.scroll_policies
+                        .set([Some(value.get().unwrap()), vpolicy]);
+                    self.obj().queue_resize();
+                }
+                "vscroll-policy" => {
+                    let [hpolicy, _] = self.scroll_policies.get();
+                    self.scroll_policies
+                        .set([hpolicy, Some(value.get().unwrap())]);
+                    self.obj().queue_resize();
+                }
+                _ => unimplemented!(),
+            }
+        }
+
+        fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+            let [hpolicy, vpolicy] = self.scroll_policies.get();
+            match pspec.name() {
+                "hadjustment" => self.adjustment(gtk::Orientation::Horizontal).to_value(),
+                "vadjustment" => self.adjustment(gtk::Orientation::Vertical).to_value(),
+                "hscroll-policy" => hpolicy.unwrap_or(gtk::ScrollablePolicy::Minimum).to_value(),
+                "vscroll-policy" => vpolicy.unwrap_or(gtk::ScrollablePolicy::Minimum).to_value(),
+                _ => unimplemented!(),
+            }
+        }
+
         fn constructed(&self) {
             self.parent_constructed();
 
-            init_epoxy();
-
             let obj = self.obj().clone();
             obj.set_can_focus(true);
             obj.set_focusable(true);
-            obj.set_hexpand(true);
-            obj.set_vexpand(true);
+            self.coalesce_input.set(true);
+
+            viewports::install_immediate_renderer();
+            self.memory_monitor.replace(Some(memory::watch(&obj)));
+
+            // The controllers are owned by the area, so they must not keep it alive
+            input::register_controllers(
+                obj.upcast_ref(),
+                Rc::new(clone!(
+                    #[weak]
+                    obj,
+                    move |event| obj.imp().push_event(event)
+                )),
+            );
+        }
+    }
 
-            self.register_controllers();
+    impl WidgetImpl for EguiArea {
+        fn contains(&self, x: f64, y: f64) -> bool {
+            if !self.parent_contains(x, y) {
+                return false;
+            }
+            if !self.input_passthrough.get() {
+                return true;
+            }
 
-            let last_render = Cell::new(Instant::now());
-            obj.add_tick_callback(move |area, _frame_clock| {
-                let should_render = match area.imp().min_render_interval.get() {
-                    Some(min_interval) => last_render.get().elapsed() > min_interval,
-                    None => true,
+            let pos = egui::pos2(x as f32, y as f32);
+            self.pointer_targets.borrow().contains(pos)
+                || self
+                    .egui_ctx()
+                    .viewport_for(self.viewport_id(), |viewport| {
+                        viewport.prev_frame.widgets.layers().any(|(_, widgets)| {
+                            widgets.iter().any(|widget| {
+                                widget.enabled
+                                    && widget.sense.interactive()
+                                    && widget.interact_rect.contains(pos)
+                            })
+                        })
+                    })
+        }
+
+        fn measure(&self, orientation: gtk::Orientation, for_size: i32) -> (i32, i32, i32, i32) {
+            let (min, natural) = if self.size_fn.borrow().is_some() {
+                // Nothing was drawn yet, so lay out the content once for the size function
+                if self.content_size.get().is_none() {
+                    let obj = self.obj();
+                    self.measured_size(egui::vec2(obj.width() as f32, obj.height() as f32));
+                    self.update_content_size(false);
+                }
+                self.content_size.get().unwrap_or_default()
+            } else {
+                let for_size = match for_size {
+                    -1 => sizing::UNCONSTRAINED,
+                    for_size => for_size as f32,
                 };
+                let screen_size = match orientation {
+                    gtk::Orientation::Horizontal => egui::vec2(sizing::UNCONSTRAINED, for_size),
+                    _ => egui::vec2(for_size, sizing::UNCONSTRAINED),
+                };
+                (egui::Vec2::ZERO, self.measured_size(screen_size))
+            };
 
-                if should_render {
-                    area.queue_render();
-                    last_render.set(Instant::now());
+            let (min, natural) = match orientation {
+                gtk::Orientation::Horizontal => (min.x, natural.x),
+                _ => (min.y, natural.y),
+            };
+            (min.ceil() as i32, natural.max(min).ceil() as i32, -1, -1)
+        }
+
+        fn size_allocate(&self, width: i32, height: i32, baseline: i32) {
+            self.parent_size_allocate(width, height, baseline);
+            self.configure_adjustments();
+
+            for child in self.embedded_children.borrow().iter() {
+                let Some(bin) = self.clip_bin(&child.widget) else {
+                    continue;
+                };
+                if !child.clip_rect.is_positive() {
+                    continue;
                 }
-                glib::ControlFlow::Continue
-            });
+
+                // The bin covers the visible part of the widget, so GTK neither draws nor picks the rest
+                let clip = gtk::Allocation::new(
+                    child.clip_rect.min.x.round() as i32,
+                    child.clip_rect.min.y.round() as i32,
+                    child.clip_rect.width().round() as i32,
+                    child.clip_rect.height().round() as i32,
+                );
+                bin.set_child_rect(
+                    child
+                        .rect
+                        .translate(-egui::vec2(clip.x() as f32, clip.y() as f32)),
+                );
+                bin.size_allocate(&clip, -1);
+            }
+        }
+
+        fn snapshot(&self, snapshot: &gtk::Snapshot) {
+            if self.software.borrow().is_some() {
+                self.render_software(snapshot);
+            } else {
+                self.parent_snapshot(snapshot);
+            }
+
+            let obj = self.obj();
+            for bin in self.clip_bins.borrow().iter() {
+                obj.snapshot_child(bin, snapshot);
+            }
+        }
+
+        fn map(&self) {
+            self.parent_map();
+            self.follow_toplevel();
+            self.update_render_loop();
+        }
+
+        fn unmap(&self) {
+            self.parent_unmap();
+            if let Some((toplevel, handler)) = self.toplevel.take() {
+                toplevel.disconnect(handler);
+            }
+            self.update_render_loop();
+        }
+
+        fn state_flags_changed(&self, previous: &gtk::StateFlags) {
+            self.parent_state_flags_changed(previous);
+            self.update_render_loop();
         }
-    }
 
-    impl WidgetImpl for EguiArea {
         fn realize(&self) {
             self.parent_realize();
 
-            self.obj().make_current();
-            let gl = unsafe { glow::Context::from_loader_function(epoxy::get_proc_addr) };
-            #[allow(clippy::arc_with_non_send_sync)]
-            let gl = Arc::new(gl);
-            *self.painter.borrow_mut() = Some(egui_glow::Painter::new(gl, "", None).unwrap());
+            let painter = self
+                .shared()
+                .painter(self.obj().upcast_ref(), &self.painter_options.borrow());
+            match painter {
+                Ok(painter) => *self.painter.borrow_mut() = Some(painter),
+                Err(err) => {
+                    if !software::is_forced() {
+                        glib::g_warning!(
+                            "gtk-egui-area",
+                            "Could not use OpenGL, falling back to software rendering: {err}"
+                        );
+                        self.obj().emit_by_name::<()>("render-error", &[&err]);
+                    }
+                    *self.software.borrow_mut() = Some(SoftwarePainter::default());
+                }
+            }
         }
 
         fn unrealize(&self) {
             self.parent_unrealize();
-            if let Some(mut painter) = self.painter.borrow_mut().take() {
-                painter.destroy();
-            }
+            self.painter.borrow_mut().take();
+            self.software.borrow_mut().take();
+            self.drawn_content.take();
         }
     }
 
+    impl ScrollableImpl for EguiArea {}
+
     impl GLAreaImpl for EguiArea {
+        fn create_context(&self) -> Option<GLContext> {
+            if software::is_forced() {
+                return None;
+            }
+            // Areas with the same egui context draw with one GL context per display, so they can use the same textures
+            self.shared()
+                .gl_context(&self.obj().display())
+                .or_else(|| self.parent_create_context())
+        }
+
         fn render(&self, _context: &GLContext) -> glib::Propagation {
             let screen_size_pixels = self.native_size();
-            let bg_color = self.egui_ctx.style().visuals.window_fill();
-
-            let focused = self.obj().has_focus();
 
-            let mut painter_guard = self.painter.borrow_mut();
-            let painter = painter_guard.as_mut().unwrap();
-            painter.clear(screen_size_pixels, bg_color.to_normalized_gamma_f32());
-
-            if let Some(run_ui) = self.run_ui.borrow().as_ref() {
-                let input_events: Vec<egui::Event> =
-                    std::mem::take(self.input_events.borrow_mut().as_mut());
-
-                let input = egui::RawInput {
-                    events: input_events,
-                    screen_rect: Some(egui::Rect::from_min_size(
-                        Default::default(),
-                        egui::Vec2::new(self.obj().width() as f32, self.obj().height() as f32),
-                    )),
-                    viewports: [(
-                        egui::ViewportId::ROOT,
-                        egui::ViewportInfo {
-                            native_pixels_per_point: Some(self.scale_factor()),
-                            focused: Some(focused),
-                            ..Default::default()
-                        },
-                    )]
-                    .into_iter()
-                    .collect(),
-                    focused,
-                    ..egui::RawInput::default()
-                };
+            // The UI runs before the painter is borrowed, so it can access the GL context of the area
+            let clipped_primitives = self.current_frame();
+            // The UI may have made other GL contexts current, e.g. by rendering paintables with GSK
+            self.obj().make_current();
 
-                let full_output = self.egui_ctx.run(input, run_ui);
+            // Without a painter the area is drawn in software and GTK doesn't render the GL area
+            let Some(gl_painter) = self.painter.borrow().clone() else {
+                return glib::Propagation::Stop;
+            };
+            let painter = &mut *gl_painter.painter.borrow_mut();
+            painter.clear(
+                screen_size_pixels,
+                self.background_color().to_normalized_gamma_f32(),
+            );
 
-                self.handle_platform_output(full_output.platform_output);
+            self.run_render_hook(&self.pre_render, painter.gl(), screen_size_pixels);
 
-                let clipped_primitives = self
-                    .egui_ctx
-                    .tessellate(full_output.shapes, full_output.pixels_per_point);
-                painter.paint_and_update_textures(
+            // A new painter gets all textures, including the font atlas, since nothing was synced to it yet
+            self.textures()
+                .borrow()
+                .sync(painter, &mut gl_painter.synced_textures.borrow_mut());
+            if let Some(clipped_primitives) = clipped_primitives {
+                painter.paint_primitives(
                     screen_size_pixels,
-                    self.egui_ctx.pixels_per_point(),
+                    self.pixels_per_point(),
                     &clipped_primitives,
-                    &full_output.textures_delta,
                 );
             }
 
+            self.run_render_hook(&self.post_render, painter.gl(), screen_size_pixels);
+            self.deliver_captures(|| painter.read_screen_rgba(screen_size_pixels));
+
+            if unsafe { painter.gl().get_error() } == glow::CONTEXT_LOST {
+                self.shared().discard(&gl_painter);
+                super::replace_lost_context(self.obj().upcast_ref());
+            }
+
             glib::Propagation::Stop
         }
     }
 
     impl EguiArea {
-        fn scale_factor(&self) -> f32 {
-            if let Some(native) = self.obj().native() {
-                if let Some(surface) = native.surface() {
-                    // TODO: with gtk 4.12+ this can be float
-                    return surface.scale_factor() as f32;
+        pub(super) fn shared(&self) -> &Rc<SharedContext> {
+            self.shared
+                .get_or_init(|| SharedContext::attach(egui::Context::default()))
+        }
+
+        pub(super) fn egui_ctx(&self) -> &egui::Context {
+            &self.shared().ctx
+        }
+
+        /// The viewport that the UI of the area runs in.
+        pub(crate) fn viewport_id(&self) -> egui::ViewportId {
+            match self.area_viewport.get() {
+                Some(viewport_id) => viewport_id,
+                None => {
+                    let viewport_id = self.shared().acquire_viewport();
+                    self.area_viewport.set(Some(viewport_id));
+                    viewport_id
                 }
             }
-            1.0
         }
 
-        fn native_size(&self) -> [u32; 2] {
-            let scale_factor = self.scale_factor();
+        /// The pixels per point of the last frame, which can also be read outside of a frame.
+        fn pixels_per_point(&self) -> f32 {
+            self.egui_ctx()
+                .input_for(self.viewport_id(), |input| input.pixels_per_point)
+        }
+
+        /// Run a frame and draw it on the CPU, for when OpenGL is not available.
+        fn render_software(&self, snapshot: &gtk::Snapshot) {
+            let screen_size_pixels = self.native_size();
+            let clipped_primitives = self.current_frame().unwrap_or_default();
+
+            let mut software = self.software.borrow_mut();
+            let Some(software) = software.as_mut() else {
+                return;
+            };
+            software.paint(
+                &self.textures().borrow(),
+                screen_size_pixels,
+                self.pixels_per_point(),
+                self.background_color(),
+                &clipped_primitives,
+            );
+            self.deliver_captures(|| software.frame().clone());
 
-            let width = self.obj().width() as f32;
-            let height = self.obj().height() as f32;
-            [
-                (width * scale_factor) as u32,
-                (height * scale_factor) as u32,
-            ]
+            let obj = self.obj();
+            software.snapshot(snapshot, obj.width() as f32, obj.height() as f32);
         }
 
-        fn handle_platform_output(&self, output: egui::PlatformOutput) {
-            if !output.copied_text.is_empty() {
-                let clipboard = self.obj().clipboard();
-                clipboard.set_text(&output.copied_text);
+        /// Watch the state of the window that the area is in, to pause rendering while it's minimized.
+        fn follow_toplevel(&self) {
+            let toplevel = self
+                .obj()
+                .native()
+                .and_then(|native| native.surface())
+                .and_then(|surface| surface.downcast::<gdk::Toplevel>().ok());
+            if let Some(toplevel) = toplevel {
+                let handler = toplevel.connect_state_notify(clone!(
+                    #[weak(rename_to = obj)]
+                    self.obj(),
+                    move |_| obj.imp().update_render_loop()
+                ));
+                self.toplevel.replace(Some((toplevel, handler)));
             }
+        }
 
-            if let Some(url) = output.open_url {
-                let window = self
-                    .obj()
-                    .root()
-                    .and_then(|root| root.downcast::<gtk::Window>().ok());
-                gtk::show_uri(window.as_ref(), &url.url, 0);
+        /// Whether the area can currently be seen, so that rendering it is useful.
+        fn is_shown(&self) -> bool {
+            let obj = self.obj();
+            if !obj.is_mapped() {
+                return false;
+            }
+            if self.pause_in_backdrop.get() && obj.state_flags().contains(gtk::StateFlags::BACKDROP)
+            {
+                return false;
             }
+
+            #[cfg(feature = "v4_12")]
+            let hidden_states = gdk::ToplevelState::MINIMIZED | gdk::ToplevelState::SUSPENDED;
+            #[cfg(not(feature = "v4_12"))]
+            let hidden_states = gdk::ToplevelState::MINIMIZED;
+            !self
+                .toplevel
+                .borrow()
+                .as_ref()
+                .is_some_and(|(toplevel, _)| toplevel.state().intersects(hidden_states))
         }
 
-        fn register_controllers(&self) {
-            let obj = self.obj().clone();
-            let current_modifiers = Rc::new(Cell::new(egui::Modifiers::default()));
-
-            let gesture_click = gtk::GestureClick::new();
-            gesture_click.connect_pressed(clone!(
-                #[strong]
-                current_modifiers,
-                #[strong]
-                obj,
-                move |_gesture, _num, x, y| {
-                    obj.grab_focus();
-                    let mut events = obj.imp().input_events.borrow_mut();
-
-                    events.push(egui::Event::PointerButton {
-                        pos: egui::pos2(x as f32, y as f32),
-                        button: egui::PointerButton::Primary,
-                        pressed: true,
-                        modifiers: current_modifiers.get(),
-                    });
+        /// Start the render loop when the area is shown and stop it when it's hidden,
+        /// so neither the UI function nor the renderer run while nobody can see the area.
+        pub(super) fn update_render_loop(&self) {
+            let shown = self.is_shown();
+            let mut tick_callback = self.tick_callback.borrow_mut();
+            if !shown {
+                if let Some(tick_callback) = tick_callback.take() {
+                    tick_callback.remove();
                 }
-            ));
-            gesture_click.connect_released(clone!(
-                #[strong]
-                current_modifiers,
-                #[strong]
-                obj,
-                move |_gesture, _num, x, y| {
-                    let mut events = obj.imp().input_events.borrow_mut();
-                    events.push(egui::Event::PointerButton {
-                        pos: egui::pos2(x as f32, y as f32),
-                        button: egui::PointerButton::Primary,
-                        pressed: false,
-                        modifiers: current_modifiers.get(),
-                    });
+                return;
+            }
+            if tick_callback.is_some() {
+                return;
+            }
+
+            // Frame times are in microseconds
+            let last_frame_time = Cell::new(None::<i64>);
+            *tick_callback = Some(self.obj().add_tick_callback(move |area, frame_clock| {
+                if area
+                    .imp()
+                    .render_due(&last_frame_time, frame_clock.frame_time())
+                    && area.imp().prepare_frame()
+                {
+                    area.queue_render();
                 }
-            ));
+                glib::ControlFlow::Continue
+            }));
+            drop(tick_callback);
 
-            let obj = self.obj().clone();
-            let event_controller_motion = gtk::EventControllerMotion::new();
-            event_controller_motion.connect_motion(move |_motion, x, y| {
-                let mut events = obj.imp().input_events.borrow_mut();
-                events.push(egui::Event::PointerMoved(egui::pos2(x as f32, y as f32)));
+            // Catch up with everything that happened while the area was hidden
+            self.obj().queue_render();
+        }
+
+        /// Whether a tick at `frame_time` renders under the frame rate policy,
+        /// recording it in `last_frame_time` if it does.
+        pub(crate) fn render_due(
+            &self,
+            last_frame_time: &Cell<Option<i64>>,
+            frame_time: i64,
+        ) -> bool {
+            let due = match (self.min_render_interval(), last_frame_time.get()) {
+                (Some(min_interval), Some(last_frame_time)) => {
+                    Duration::from_micros((frame_time - last_frame_time).max(0) as u64)
+                        >= min_interval
+                }
+                _ => true,
+            };
+            if due {
+                last_frame_time.set(Some(frame_time));
+            }
+            due
+        }
+
+        /// The shortest time between two renders under the frame rate policy.
+        fn frame_rate_limit(&self) -> Option<Duration> {
+            let backdrop = self.obj().state_flags().contains(gtk::StateFlags::BACKDROP);
+            self.frame_rate_policy.get().min_interval(
+                frame_rate::power_saver_enabled(),
+                backdrop,
+                self.refresh_rate(),
+            )
+        }
+
+        /// The shortest time between two ticks that render.
+        fn min_render_interval(&self) -> Option<Duration> {
+            let min_interval = self.frame_rate_limit()?;
+            // Ticks only happen once per refresh, so a tick that comes slightly early has to render as well
+            let refresh_rate = self
+                .refresh_rate()
+                .unwrap_or(frame_rate::DEFAULT_REFRESH_RATE);
+            Some(min_interval.saturating_sub(Duration::from_secs_f32(0.5 / refresh_rate)))
+        }
+
+        /// The time of the current frame of the frame clock and the expected time until the next rendered frame, in seconds.
+        pub(crate) fn frame_timing(&self) -> (Option<f64>, f32) {
+            let refresh_interval = 1.0
+                / self
+                    .refresh_rate()
+                    .unwrap_or(frame_rate::DEFAULT_REFRESH_RATE);
+            let Some(frame_clock) = self.obj().frame_clock() else {
+                return (None, refresh_interval);
+            };
+
+            let frame_time = frame_clock.frame_time();
+            let refresh_interval = match frame_clock.refresh_info(frame_time) {
+                (interval, _) if interval > 0 => interval as f32 / 1_000_000.0,
+                _ => refresh_interval,
+            };
+            // With a frame rate limit, only some of the refreshes are rendered
+            let predicted_dt = match self.frame_rate_limit() {
+                Some(min_interval) => {
+                    (min_interval.as_secs_f32() / refresh_interval)
+                        .ceil()
+                        .max(1.0)
+                        * refresh_interval
+                }
+                None => refresh_interval,
+            };
+            (Some(frame_time as f64 / 1_000_000.0), predicted_dt)
+        }
+
+        /// The refresh rate of the monitor that shows the area, in frames per second.
+        fn refresh_rate(&self) -> Option<f32> {
+            let obj = self.obj();
+            let surface = obj.native()?.surface()?;
+            let monitor = obj.display().monitor_at_surface(&surface)?;
+            // The refresh rate is in millihertz
+            let refresh_rate = monitor.refresh_rate();
+            (refresh_rate > 0).then(|| refresh_rate as f32 / 1000.0)
+        }
+
+        fn background_color(&self) -> egui::Color32 {
+            self.clear_color
+                .get()
+                .unwrap_or_else(|| self.egui_ctx().style().visuals.window_fill())
+        }
+
+        /// Send the rendered frame to pending captures and screenshot requests.
+        fn deliver_captures(&self, read_frame: impl FnOnce() -> egui::ColorImage) {
+            let screenshot_requested = self.screenshot_requested.take();
+            let captures = self.captures.take();
+            if !screenshot_requested && captures.is_empty() {
+                return;
+            }
+
+            let image = Arc::new(read_frame());
+            for capture in captures {
+                let _ = capture.send(image.clone());
+            }
+            if screenshot_requested {
+                // Delivered to egui on the next frame
+                self.push_event(egui::Event::Screenshot {
+                    viewport_id: self.viewport_id(),
+                    image,
+                });
+                self.obj().queue_render();
+            }
+        }
+
+        fn raw_input(&self) -> egui::RawInput {
+            let focused = self.obj().has_focus();
+            let input_events: Vec<egui::Event> =
+                std::mem::take(self.input_events.borrow_mut().as_mut());
+            *self.pointer_history.borrow_mut() = self.pending_pointer_history.take();
+
+            let widget_size =
+                egui::Vec2::new(self.obj().width() as f32, self.obj().height() as f32);
+            let scroll_offset = self.scroll_offset();
+            let (time, predicted_dt) = self.frame_timing();
+            let screen_size = if scroll_offset.is_some() {
+                widget_size.max(self.scroll_content_size())
+            } else {
+                widget_size
+            };
+
+            egui::RawInput {
+                viewport_id: self.viewport_id(),
+                events: input_events,
+                screen_rect: Some(egui::Rect::from_min_size(
+                    (-scroll_offset.unwrap_or_default()).to_pos2(),
+                    screen_size,
+                )),
+                viewports: self.viewport_infos(),
+                time,
+                predicted_dt,
+                focused,
+                ..egui::RawInput::default()
+            }
+        }
+
+        /// The state of the area and all viewport windows, as reported to egui.
+        pub(crate) fn viewport_infos(&self) -> egui::ViewportIdMap<egui::ViewportInfo> {
+            let mut infos = self.viewports.borrow().infos();
+            infos.insert(
+                self.viewport_id(),
+                egui::ViewportInfo {
+                    native_pixels_per_point: Some(self.scale_factor()),
+                    focused: Some(self.obj().has_focus()),
+                    ..Default::default()
+                },
+            );
+            infos
+        }
+
+        /// Run the UI in the update phase of the frame clock, before GTK lays out and draws the widgets.
+        ///
+        /// Input from the current frame shows up right away, and changes to the content size and
+        /// embedded widgets are laid out in the same frame.
+        /// Returns `false` if the output is the same as the frame that is already shown, so there is nothing to render.
+        fn prepare_frame(&self) -> bool {
+            // The UI may use the GL context of the area
+            if self.painter.borrow().is_some() {
+                self.obj().make_current();
+            }
+            let Some(full_output) = self.run_ui_frame(true) else {
+                self.prepared_frame.take();
+                return true;
+            };
+
+            let size = self.native_size();
+            // Render hooks may draw something different on every frame
+            let unchanged = full_output.textures_delta.is_empty()
+                && self.pre_render.borrow().is_none()
+                && self.post_render.borrow().is_none()
+                && self.drawn_content.borrow().as_ref().is_some_and(|drawn| {
+                    drawn.size == size
+                        && drawn.pixels_per_point == full_output.pixels_per_point
+                        && drawn.shapes == full_output.shapes
+                });
+
+            let pixels_per_point = full_output.pixels_per_point;
+            let shapes = self.handle_output(full_output);
+            if unchanged && !self.screenshot_requested.get() && self.captures.borrow().is_empty() {
+                self.skipped_frames.set(self.skipped_frames.get() + 1);
+                self.prepared_frame.take();
+                return false;
+            }
+
+            // Only frames that are drawn are tessellated
+            let clipped_primitives = self.egui_ctx().tessellate(shapes.clone(), pixels_per_point);
+            let content = FrameContent {
+                shapes,
+                pixels_per_point,
+                size,
+            };
+            *self.prepared_frame.borrow_mut() = Some(PreparedFrame {
+                clipped_primitives,
+                content,
             });
-            let obj = self.obj().clone();
-            event_controller_motion.connect_leave(move |_motion| {
-                let mut events = obj.imp().input_events.borrow_mut();
-                events.push(egui::Event::PointerGone);
+            true
+        }
+
+        /// The frame prepared in the update phase, or a new one when there is none
+        /// or the area changed its size since then.
+        fn current_frame(&self) -> Option<Vec<egui::ClippedPrimitive>> {
+            match self.prepared_frame.take() {
+                Some(frame) if frame.content.size == self.native_size() => {
+                    self.drawn_content.replace(Some(frame.content));
+                    Some(frame.clipped_primitives)
+                }
+                _ => {
+                    // The content of a frame that wasn't prepared isn't known, so the next frame is always drawn
+                    self.drawn_content.take();
+                    self.run_frame()
+                }
+            }
+        }
+
+        /// Run the egui UI function for a single frame and tessellate the output,
+        /// returning `None` if there is no UI function set.
+        fn run_frame(&self) -> Option<Vec<egui::ClippedPrimitive>> {
+            let full_output = self.run_ui_frame(false)?;
+            Some(self.finish_frame(full_output))
+        }
+
+        /// Run the egui UI function for a single frame and lay out the GTK parts of the UI,
+        /// returning `None` if there is no UI function set.
+        ///
+        /// In the `update_phase` of the frame clock, changes to the size and embedded widgets are applied right away,
+        /// otherwise GTK is drawing and they are applied afterwards.
+        fn run_ui_frame(&self, update_phase: bool) -> Option<egui::FullOutput> {
+            let run_ui = self.run_ui.borrow();
+            let run_ui = run_ui.as_ref()?;
+
+            let popup_bounds = self
+                .external_popups
+                .get()
+                .then(|| popups::popup_bounds(&self.obj()))
+                .flatten();
+
+            let mut popup_layers = Vec::new();
+            let full_output = embed::with_current_area(&self.obj(), || {
+                self.egui_ctx().run(self.raw_input(), |ctx| {
+                    let screen_rect = ctx.screen_rect();
+                    if let Some(popup_bounds) = popup_bounds {
+                        // Panels are already laid out in the available rect at this point,
+                        // so this only affects where areas like popups can be placed
+                        ctx.input_mut(|input| input.screen_rect = popup_bounds.union(screen_rect));
+                    }
+
+                    run_ui(ctx);
+
+                    if popup_bounds.is_some() {
+                        popup_layers = popups::take_overflowing_layers(ctx, screen_rect);
+                    }
+                })
             });
+            // egui only keeps some of this for the viewport that ran last, which may be of another area
+            *self.pointer_targets.borrow_mut() =
+                input::PointerTargets::of_last_frame(self.egui_ctx());
+            self.update_popups(popup_layers, full_output.pixels_per_point);
+            self.update_content_size(update_phase);
+            self.update_embedded_children(update_phase);
 
-            let obj = self.obj().clone();
+            Some(full_output)
+        }
+        /// Handle the output of a frame of the area, returning the tessellated shapes.
+        /// Handle the output of a frame of any viewport, returning the tessellated shapes.
+        pub(crate) fn finish_frame(
+            &self,
+            full_output: egui::FullOutput,
+        ) -> Vec<egui::ClippedPrimitive> {
+            let pixels_per_point = full_output.pixels_per_point;
+            let shapes = self.handle_output(full_output);
+            self.egui_ctx().tessellate(shapes, pixels_per_point)
+        }
+
+        /// Handle everything in the output of a frame of any viewport apart from the shapes, which are returned.
+        ///
+        /// Texture changes are recorded in the texture store, which every painter syncs its textures from.
+        pub(crate) fn handle_output(
+            &self,
+            full_output: egui::FullOutput,
+        ) -> Vec<egui::epaint::ClippedShape> {
+            self.textures()
+                .borrow_mut()
+                .apply(&full_output.textures_delta);
+
+            self.handle_platform_output(full_output.platform_output);
+            self.handle_viewport_output(full_output.viewport_output);
+
+            full_output.shapes
+        }
 
-            let event_controller_scroll = gtk::EventControllerScroll::new(
-                gtk::EventControllerScrollFlags::BOTH_AXES
-                    | gtk::EventControllerScrollFlags::DISCRETE,
+        /// Handle the commands for the area and its viewport windows.
+        fn handle_viewport_output(&self, output: egui::ViewportIdMap<egui::ViewportOutput>) {
+            if let Some(root) = output.get(&self.viewport_id()) {
+                if root.commands.contains(&egui::ViewportCommand::Screenshot) {
+                    self.screenshot_requested.set(true);
+                }
+            }
+            self.update_viewports(output);
+        }
+
+        fn run_render_hook(
+            &self,
+            hook: &RefCell<Option<DynRenderHook>>,
+            gl: &glow::Context,
+            screen_size_px: [u32; 2],
+        ) {
+            let hook = hook.borrow();
+            let Some(hook) = hook.as_ref() else {
+                return;
+            };
+
+            let pixels_per_point = self.pixels_per_point();
+            let screen_rect = egui::Rect::from_min_size(
+                egui::Pos2::ZERO,
+                egui::vec2(screen_size_px[0] as f32, screen_size_px[1] as f32) / pixels_per_point,
             );
-            event_controller_scroll.connect_scroll(clone!(
-                #[strong]
-                current_modifiers,
-                move |_scroll, x, y| {
-                    let mut events = obj.imp().input_events.borrow_mut();
-
-                    events.push(egui::Event::MouseWheel {
-                        unit: egui::MouseWheelUnit::Line,
-                        delta: egui::Vec2::new(-x as f32, -y as f32),
-                        modifiers: current_modifiers.get(),
-                    });
-                    glib::Propagation::Proceed
+            let info = egui::PaintCallbackInfo {
+                viewport: screen_rect,
+                clip_rect: screen_rect,
+                pixels_per_point,
+                screen_size_px,
+            };
+
+            unsafe {
+                gl.viewport(0, 0, screen_size_px[0] as i32, screen_size_px[1] as i32);
+            }
+            custom_paint::with_restored_framebuffer(gl, || hook(gl, &info));
+        }
+
+        /// The size of the content when it's laid out in a screen of `screen_size`, in points.
+        ///
+        /// The last result is kept until the layout of the content changes.
+        fn measured_size(&self, screen_size: egui::Vec2) -> egui::Vec2 {
+            if let Some((measured_screen_size, size)) = self.last_measurement.get() {
+                if measured_screen_size == screen_size {
+                    return size;
+                }
+            }
+
+            let size = self.sizing_pass(screen_size);
+            self.last_measurement.set(Some((screen_size, size)));
+            size
+        }
+
+        /// Run a frame without drawing it or passing input to it, to find out the size of the content.
+        ///
+        /// The frame runs in a viewport of its own, so the widgets of the last frame of the area,
+        /// which the next frame is hit-tested against, stay the same. The egui memory is restored afterwards,
+        /// so the layout doesn't affect the state of the UI, such as scroll offsets that would otherwise be
+        /// clamped to the bigger screen.
+        fn sizing_pass(&self, screen_size: egui::Vec2) -> egui::Vec2 {
+            let run_ui = self.run_ui.borrow();
+            let Some(run_ui) = run_ui.as_ref() else {
+                return egui::Vec2::ZERO;
+            };
+
+            let ctx = self.egui_ctx();
+            let viewport_id = sizing::viewport_id();
+            let memory = ctx.memory(|memory| memory.clone());
+            let embed_viewports = ctx.embed_viewports();
+            // Viewports are shown as egui windows, so the pass doesn't open GTK windows
+            ctx.set_embed_viewports(true);
+
+            let (time, predicted_dt) = self.frame_timing();
+            let raw_input = egui::RawInput {
+                viewport_id,
+                screen_rect: Some(egui::Rect::from_min_size(egui::Pos2::ZERO, screen_size)),
+                viewports: std::iter::once((
+                    viewport_id,
+                    egui::ViewportInfo {
+                        native_pixels_per_point: Some(self.scale_factor()),
+                        ..Default::default()
+                    },
+                ))
+                .collect(),
+                time,
+                predicted_dt,
+                ..egui::RawInput::default()
+            };
+            let full_output = embed::with_current_area(&self.obj(), || ctx.run(raw_input, run_ui));
+            ctx.set_embed_viewports(embed_viewports);
+
+            // Font glyphs added while laying out the content are only sent with this output
+            self.textures()
+                .borrow_mut()
+                .apply(&full_output.textures_delta);
+            // So are the commands that were sent to the windows of the area since its last frame
+            self.handle_viewport_output(full_output.viewport_output);
+            self.placed_children.take();
+
+            ctx.memory_mut(|current| {
+                let caches = std::mem::take(&mut current.caches);
+                *current = memory;
+                current.caches = caches;
+            });
+            sizing::content_size(ctx, viewport_id, screen_size)
+        }
+
+        pub(crate) fn push_event(&self, event: egui::Event) {
+            if let egui::Event::PointerMoved(pos) = event {
+                self.pending_pointer_history.borrow_mut().push(pos);
+            }
+
+            let mut input_events = self.input_events.borrow_mut();
+            if self.coalesce_input.get() {
+                if let Some(previous) = input_events.last_mut() {
+                    if input::coalesce(previous, &event) {
+                        return;
+                    }
+                }
+            }
+            input_events.push(event);
+        }
+
+        pub(crate) fn textures(&self) -> Rc<RefCell<TextureStore>> {
+            self.shared().textures.clone()
+        }
+
+        fn update_popups(&self, layers: Vec<popups::PopupLayer>, pixels_per_point: f32) {
+            if layers.is_empty() && self.popups.borrow().is_empty() {
+                return;
+            }
+
+            // Popovers can't be shown or moved while GTK is drawing
+            glib::idle_add_local_once(clone!(
+                #[weak(rename_to = obj)]
+                self.obj(),
+                move || {
+                    if obj.imp().disposed.get() {
+                        return;
+                    }
+                    obj.imp()
+                        .popups
+                        .borrow_mut()
+                        .update(&obj, layers, pixels_per_point);
                 }
             ));
+        }
 
-            let obj = self.obj().clone();
-            let event_controller_key = gtk::EventControllerKey::new();
-            event_controller_key.connect_key_pressed(move |_controller, key, _code, modifiers| {
-                let mut events = obj.imp().input_events.borrow_mut();
-
-                if modifiers.is_empty() || modifiers.contains(gtk::gdk::ModifierType::SHIFT_MASK) {
-                    if let Some(char) = key.to_unicode() {
-                        if !char.is_control() {
-                            events.push(egui::Event::Text(char.into()));
-                        }
+        fn update_viewports(&self, output: egui::ViewportIdMap<egui::ViewportOutput>) {
+            // The output has the viewports of all areas that share the egui context
+            let output = viewports::children_of(self.viewport_id(), output);
+            if output.is_empty() && self.viewports.borrow().is_empty() {
+                return;
+            }
+
+            // Windows can't be opened or closed while GTK is drawing
+            glib::idle_add_local_once(clone!(
+                #[weak(rename_to = obj)]
+                self.obj(),
+                move || {
+                    if obj.imp().disposed.get() {
+                        return;
                     }
+                    obj.imp().viewports.borrow_mut().update(&obj, output);
                 }
+            ));
+        }
+
+        pub(crate) fn place_child(&self, child: EmbeddedChild) {
+            if child.widget.parent().is_some() && self.clip_bin(&child.widget).is_none() {
+                glib::g_warning!(
+                    "gtk-egui-area",
+                    "Cannot place widget {} that already has a different parent",
+                    child.widget.type_().name()
+                );
+                return;
+            }
+            self.placed_children.borrow_mut().push(child);
+        }
+
+        /// The wrapper that `widget` is embedded in, if it's embedded in this area.
+        fn clip_bin(&self, widget: &gtk::Widget) -> Option<ClipBin> {
+            self.clip_bins
+                .borrow()
+                .iter()
+                .find(|bin| bin.child().as_ref() == Some(widget))
+                .cloned()
+        }
+
+        fn update_embedded_children(&self, update_phase: bool) {
+            let placed_children = self.placed_children.take();
+            if *self.embedded_children.borrow() == placed_children {
+                return;
+            }
+            *self.embedded_children.borrow_mut() = placed_children;
 
-                if let Some(key) = gdk_to_egui_key(key) {
-                    let modifiers = gdk_to_egui_modifiers(modifiers);
-
-                    if is_copy_command(modifiers, key) {
-                        events.push(egui::Event::Copy);
-                    } else if is_cut_command(modifiers, key) {
-                        events.push(egui::Event::Cut);
-                    } else if is_paste_command(modifiers, key) {
-                        let clipboard = obj.clipboard();
-                        let obj = obj.clone();
-                        clipboard.read_text_async(gio::Cancellable::NONE, move |result| {
-                            if let Ok(Some(text)) = result {
-                                obj.imp()
-                                    .input_events
-                                    .borrow_mut()
-                                    .push(egui::Event::Paste(text.to_string()));
-                            }
-                        });
+            self.apply_layout_change(update_phase, |imp| {
+                let obj = imp.obj();
+                for child in imp.embedded_children.borrow().iter() {
+                    if child.widget.parent().is_none() {
+                        let bin = ClipBin::new(&child.widget);
+                        bin.set_parent(&*obj);
+                        imp.clip_bins.borrow_mut().push(bin);
                     }
+                }
 
-                    events.push(egui::Event::Key {
-                        key,
-                        physical_key: None,
-                        pressed: true,
-                        repeat: false,
-                        modifiers,
+                // Widgets that weren't placed or are scrolled out of view are hidden
+                for bin in imp.clip_bins.borrow().iter() {
+                    let visible = imp.embedded_children.borrow().iter().any(|embedded| {
+                        bin.child().as_ref() == Some(&embedded.widget)
+                            && embedded.clip_rect.is_positive()
                     });
+                    bin.set_child_visible(visible);
                 }
-                glib::Propagation::Proceed
+
+                obj.queue_allocate();
             });
-            let obj = self.obj().clone();
-            event_controller_key.connect_key_released(move |_controller, key, _code, modifiers| {
-                let mut events = obj.imp().input_events.borrow_mut();
-
-                if let Some(key) = gdk_to_egui_key(key) {
-                    events.push(egui::Event::Key {
-                        key,
-                        physical_key: None,
-                        pressed: false,
-                        repeat: false,
-                        modifiers: gdk_to_egui_modifiers(modifiers),
-                    });
+        }
+
+        fn update_content_size(&self, update_phase: bool) {
+            let changed = match self.size_fn.borrow().as_ref() {
+                Some(size_fn) => {
+                    let new_size = size_fn(self.egui_ctx());
+                    let old_size = self.content_size.replace(Some(new_size));
+                    old_size.is_some_and(|old_size| old_size != new_size)
                 }
-            });
-            event_controller_key.connect_modifiers(move |_controller, new_modifiers| {
-                current_modifiers.set(gdk_to_egui_modifiers(new_modifiers));
-                glib::Propagation::Proceed
-            });
+                // Without a size function, the content is measured again once its layout changed
+                None => {
+                    let fingerprint =
+                        sizing::layout_fingerprint(self.egui_ctx(), self.viewport_id());
+                    let old_fingerprint = self.layout_fingerprint.replace(Some(fingerprint));
+                    old_fingerprint.is_some_and(|old_fingerprint| old_fingerprint != fingerprint)
+                }
+            };
 
-            let obj = self.obj().clone();
-            obj.add_controller(event_controller_motion);
-            obj.add_controller(gesture_click);
-            obj.add_controller(event_controller_scroll);
-            obj.add_controller(event_controller_key);
-        }
-    }
-
-    fn gdk_to_egui_key(key: gtk::gdk::Key) -> Option<egui::Key> {
-        use egui::Key as EguiKey;
-        use gtk::gdk::Key;
-        let key = match key {
-            Key::BackSpace => EguiKey::Backspace,
-            Key::Down => EguiKey::ArrowDown,
-            Key::Up => EguiKey::ArrowUp,
-            Key::Left => EguiKey::ArrowLeft,
-            Key::Right => EguiKey::ArrowRight,
-            Key::KP_Enter | Key::ISO_Enter => EguiKey::Enter,
-            Key::space | Key::KP_Space => EguiKey::Space,
-            Key::Page_Up => EguiKey::PageUp,
-            Key::Page_Down => EguiKey::PageDown,
-            Key::colon => EguiKey::Colon,
-            Key::comma => EguiKey::Comma,
-            Key::backslash => EguiKey::Backslash,
-            Key::slash => EguiKey::Slash,
-            Key::vertbar => EguiKey::Pipe,
-            Key::question => EguiKey::Questionmark,
-            Key::bracketleft => EguiKey::OpenBracket,
-            Key::braceright => EguiKey::CloseBracket,
-            Key::grave => EguiKey::Backtick,
-            Key::minus => EguiKey::Minus,
-            Key::period => EguiKey::Period,
-            Key::plus => EguiKey::Plus,
-            Key::equal => EguiKey::Equals,
-            Key::semicolon => EguiKey::Semicolon,
-            Key::singlelowquotemark => EguiKey::Quote,
-            Key::_0 | Key::KP_0 => EguiKey::Num0,
-            Key::_1 | Key::KP_1 => EguiKey::Num1,
-            Key::_2 | Key::KP_2 => EguiKey::Num2,
-            Key::_3 | Key::KP_3 => EguiKey::Num3,
-            Key::_4 | Key::KP_4 => EguiKey::Num4,
-            Key::_5 | Key::KP_5 => EguiKey::Num5,
-            Key::_6 | Key::KP_6 => EguiKey::Num6,
-            Key::_7 | Key::KP_7 => EguiKey::Num7,
-            Key::_8 | Key::KP_8 => EguiKey::Num8,
-            Key::_9 | Key::KP_9 => EguiKey::Num9,
-            _ => return key.name().and_then(|name| egui::Key::from_name(&name)),
-        };
-        Some(key)
-    }
-
-    fn gdk_to_egui_modifiers(modifiers: gtk::gdk::ModifierType) -> egui::Modifiers {
-        use gtk::gdk::ModifierType;
-        egui::Modifiers {
-            alt: modifiers.contains(ModifierType::ALT_MASK),
-            ctrl: modifiers.contains(ModifierType::CONTROL_MASK),
-            shift: modifiers.contains(ModifierType::SHIFT_MASK),
-            mac_cmd: modifiers.contains(ModifierType::META_MASK),
-            #[cfg(target_os = "macos")]
-            command: modifiers.contains(ModifierType::META_MASK),
-            #[cfg(not(target_os = "macos"))]
-            command: modifiers.contains(ModifierType::CONTROL_MASK),
+            if changed {
+                self.last_measurement.take();
+                self.apply_layout_change(update_phase, |imp| {
+                    imp.configure_adjustments();
+                    imp.obj().queue_resize();
+                });
+            }
+        }
+
+        /// Apply a change to the layout of the area right away in the `update_phase` of the frame clock,
+        /// before GTK lays out the widgets.
+        ///
+        /// Widgets can't be reparented or resized while GTK is drawing, so otherwise it's applied afterwards.
+        fn apply_layout_change(&self, update_phase: bool, f: impl FnOnce(&Self) + 'static) {
+            if update_phase {
+                f(self);
+                return;
+            }
+            glib::idle_add_local_once(clone!(
+                #[weak(rename_to = obj)]
+                self.obj(),
+                move || {
+                    if obj.imp().disposed.get() {
+                        return;
+                    }
+                    f(obj.imp());
+                }
+            ));
+        }
+
+        fn adjustment(&self, orientation: gtk::Orientation) -> Option<gtk::Adjustment> {
+            let adjustment = match orientation {
+                gtk::Orientation::Horizontal => &self.hadjustment,
+                _ => &self.vadjustment,
+            };
+            adjustment
+                .borrow()
+                .as_ref()
+                .map(|(adjustment, _)| adjustment.clone())
+        }
+
+        fn set_adjustment(
+            &self,
+            orientation: gtk::Orientation,
+            adjustment: Option<gtk::Adjustment>,
+        ) {
+            let slot = match orientation {
+                gtk::Orientation::Horizontal => &self.hadjustment,
+                _ => &self.vadjustment,
+            };
+            if let Some((old_adjustment, handler)) = slot.take() {
+                old_adjustment.disconnect(handler);
+            }
+
+            let adjustment = adjustment.unwrap_or_default();
+            let handler = adjustment.connect_value_changed(clone!(
+                #[weak(rename_to = obj)]
+                self.obj(),
+                move |_| obj.queue_render()
+            ));
+            *slot.borrow_mut() = Some((adjustment, handler));
+
+            self.configure_adjustments();
+        }
+
+        /// The scroll offset of the root content, or `None` if the area is not scrollable.
+        fn scroll_offset(&self) -> Option<egui::Vec2> {
+            let hadjustment = self.adjustment(gtk::Orientation::Horizontal)?;
+            let vadjustment = self.adjustment(gtk::Orientation::Vertical)?;
+            Some(egui::vec2(
+                hadjustment.value() as f32,
+                vadjustment.value() as f32,
+            ))
+        }
+
+        /// The size of the scrollable content, which is the natural size reported by the size function,
+        /// or without one, the size of the content when it's laid out at the width of the area.
+        fn scroll_content_size(&self) -> egui::Vec2 {
+            match self.size_fn.borrow().as_ref() {
+                Some(_) => self.content_size.get().unwrap_or_default().1,
+                None => {
+                    self.measured_size(egui::vec2(self.obj().width() as f32, sizing::UNCONSTRAINED))
+                }
+            }
+        }
+
+        fn configure_adjustments(&self) {
+            let content_size = self.scroll_content_size();
+            let viewport_size = [self.obj().width(), self.obj().height()];
+
+            for (orientation, content_size, viewport_size) in [
+                (
+                    gtk::Orientation::Horizontal,
+                    content_size.x,
+                    viewport_size[0],
+                ),
+                (gtk::Orientation::Vertical, content_size.y, viewport_size[1]),
+            ] {
+                if let Some(adjustment) = self.adjustment(orientation) {
+                    let page_size = viewport_size as f64;
+                    let upper = (content_size as f64).max(page_size);
+                    adjustment.configure(
+                        adjustment.value().clamp(0.0, upper - page_size),
+                        0.0,
+                        upper,
+                        page_size * 0.1,
+                        page_size * 0.9,
+                        page_size,
+                    );
+                }
+            }
+        }
+
+        fn scale_factor(&self) -> f32 {
+            super::scale_factor(self.obj().upcast_ref())
+        }
+
+        fn native_size(&self) -> [u32; 2] {
+            super::native_size(self.obj().upcast_ref())
+        }
+
+        fn handle_platform_output(&self, output: egui::PlatformOutput) {
+            if !output.copied_text.is_empty() {
+                let clipboard = self.obj().clipboard();
+                clipboard.set_text(&output.copied_text);
+            }
+
+            if let Some(url) = output.open_url {
+                let window = self
+                    .obj()
+                    .root()
+                    .and_then(|root| root.downcast::<gtk::Window>().ok());
+                #[cfg(feature = "v4_12")]
+                gtk::UriLauncher::new(&url.url).launch(
+                    window.as_ref(),
+                    gtk::gio::Cancellable::NONE,
+                    |result| {
+                        if let Err(err) = result {
+                            glib::g_warning!("gtk-egui-area", "Could not open URL: {err}");
+                        }
+                    },
+                );
+                #[cfg(not(feature = "v4_12"))]
+                gtk::show_uri(window.as_ref(), &url.url, 0);
+            }
         }
     }
+}
 
-    fn is_cut_command(modifiers: egui::Modifiers, key: egui::Key) -> bool {
-        key == egui::Key::Cut
-            || (modifiers.command && key == egui::Key::X)
-            || (cfg!(target_os = "windows") && modifiers.shift && key == egui::Key::Delete)
+fn scale_factor(widget: &gtk::Widget) -> f32 {
+    if let Some(native) = widget.native() {
+        if let Some(surface) = native.surface() {
+            // TODO: with gtk 4.12+ this can be float
+            return surface.scale_factor() as f32;
+        }
     }
+    1.0
+}
+
+/// The size of the widget in physical pixels.
+fn native_size(widget: &gtk::Widget) -> [u32; 2] {
+    let scale_factor = scale_factor(widget);
+
+    let width = widget.width() as f32;
+    let height = widget.height() as f32;
+    [
+        (width * scale_factor) as u32,
+        (height * scale_factor) as u32,
+    ]
+}
 
-    fn is_copy_command(modifiers: egui::Modifiers, key: egui::Key) -> bool {
-        key == egui::Key::Copy
-            || (modifiers.command && key == egui::Key::C)
-            || (cfg!(target_os = "windows") && modifiers.ctrl && key == egui::Key::Insert)
+/// Create an egui painter for the GL context of a realized area.
+///
+/// Failures are also set as the error of the area, unless GTK already set one while creating the context.
+fn create_painter(
+    area: &gtk::GLArea,
+    options: &PainterOptions,
+) -> Result<egui_glow::Painter, glib::Error> {
+    if let Some(err) = area.error() {
+        return Err(err);
     }
 
-    fn is_paste_command(modifiers: egui::Modifiers, key: egui::Key) -> bool {
-        key == egui::Key::Paste
-            || (modifiers.command && key == egui::Key::V)
-            || (cfg!(target_os = "windows") && modifiers.shift && key == egui::Key::Insert)
+    let result = area
+        .context()
+        .ok_or_else(|| glib::Error::new(gdk::GLError::NotAvailable, "The area has no GL context"))
+        .and_then(|context| create_context_painter(&context, options));
+    if let Err(err) = &result {
+        area.set_error(Some(err));
     }
+    result
+}
+
+/// Create an egui painter for a realized GL context, which is made current.
+fn create_context_painter(
+    context: &gdk::GLContext,
+    options: &PainterOptions,
+) -> Result<egui_glow::Painter, glib::Error> {
+    init_epoxy()?;
+    context.make_current();
+    let shader_version = options.shader_version(context);
+    let gl = unsafe { glow::Context::from_loader_function(epoxy::get_proc_addr) };
+    #[allow(clippy::arc_with_non_send_sync)]
+    let gl = Arc::new(gl);
+    egui_glow::Painter::new(gl, &options.shader_prefix, Some(shader_version)).map_err(|err| {
+        glib::Error::new(
+            gdk::GLError::NotAvailable,
+            &format!("Could not create the egui painter: {err}"),
+        )
+    })
+}
+
+/// Realize an area again after its GL context was lost, e.g. by a GPU reset, so it gets a new context.
+///
+/// Painters sync all textures from the texture store when they are created again.
+fn replace_lost_context(area: &gtk::GLArea) {
+    glib::g_warning!(
+        "gtk-egui-area",
+        "The OpenGL context was lost, creating a new one"
+    );
+    // The area can't be realized again while it's rendering
+    glib::idle_add_local_once(glib::clone!(
+        #[weak]
+        area,
+        move || {
+            if !area.is_realized() {
+                return;
+            }
+            let mapped = area.is_mapped();
+            area.unrealize();
+            area.realize();
+            if mapped {
+                area.map();
+            }
+        }
+    ));
 }
 
-fn init_epoxy() {
-    static EPOXY_INIT: OnceLock<()> = OnceLock::new();
-
-    EPOXY_INIT.get_or_init(|| {
-        #[cfg(target_os = "macos")]
-        let library = unsafe { libloading::os::unix::Library::new("libepoxy.0.dylib") }.unwrap();
-        #[cfg(all(unix, not(target_os = "macos")))]
-        let library = unsafe { libloading::os::unix::Library::new("libepoxy.so.0") }.unwrap();
-        #[cfg(windows)]
-        let library = libloading::os::windows::Library::open_already_loaded("libepoxy-0.dll")
-            .or_else(|_| libloading::os::windows::Library::open_already_loaded("epoxy-0.dll"))
-            .unwrap();
-
-        epoxy::load_with(|name| {
-            unsafe { library.get::<_>(name.as_bytes()) }
-                .map(|symbol| *symbol)
-                .unwrap_or(ptr::null())
-        });
-    });
+/// Load libepoxy, which provides the OpenGL functions. The library is only loaded once.
+fn init_epoxy() -> Result<(), glib::Error> {
+    static EPOXY_INIT: OnceLock<Result<(), String>> = OnceLock::new();
+
+    EPOXY_INIT
+        .get_or_init(|| {
+            #[cfg(target_os = "macos")]
+            let library = unsafe { libloading::os::unix::Library::new("libepoxy.0.dylib") };
+            #[cfg(all(unix, not(target_os = "macos")))]
+            let library = unsafe { libloading::os::unix::Library::new("libepoxy.so.0") };
+            #[cfg(windows)]
+            let library = libloading::os::windows::Library::open_already_loaded("libepoxy-0.dll")
+                .or_else(|_| libloading::os::windows::Library::open_already_loaded("epoxy-0.dll"));
+            let library = library.map_err(|err| format!("Could not load libepoxy: {err}"))?;
+
+            epoxy::load_with(|name| {
+                unsafe { library.get::<_>(name.as_bytes()) }
+                    .map(|symbol| *symbol)
+                    .unwrap_or(ptr::null())
+            });
+            Ok(())
+        })
+        .clone()
+        .map_err(|err| glib::Error::new(gdk::GLError::NotAvailable, &err))
 }
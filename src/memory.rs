@@ -0,0 +1,118 @@
+use crate::EguiArea;
+use gtk::{
+    gio,
+    glib::{self, translate::IntoGlib},
+    prelude::{GLAreaExt, MemoryMonitorExt, ObjectExt},
+    subclass::prelude::ObjectSubclassIsExt,
+};
+
+/// What an [`EguiArea`] frees when the system warns that it's running low on memory.
+///
+/// Everything that is freed can be loaded or created again when the UI uses it the next time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LowMemoryPolicy {
+    /// The lowest warning level to free memory at. `None` ignores the warnings.
+    pub min_level: Option<gio::MemoryMonitorWarningLevel>,
+    /// Forget the images and bytes cached by the egui loaders, see [`egui::Context::forget_all_images`].
+    pub forget_images: bool,
+    /// Build the font atlas again on the next frame, dropping glyphs and sizes that are no longer used.
+    pub trim_font_atlas: bool,
+    /// Drop the textures that the area caches itself, such as GTK icons, and the copies of textures that the area
+    /// keeps for uploading them again, if they can be set again when they are needed.
+    ///
+    /// Those are the font atlas, [`crate::PaintableTexture`]s and textures with a reload function,
+    /// see [`crate::EguiArea::set_texture_reload`].
+    pub evict_textures: bool,
+}
+
+impl Default for LowMemoryPolicy {
+    fn default() -> Self {
+        Self {
+            min_level: Some(gio::MemoryMonitorWarningLevel::Low),
+            forget_images: true,
+            trim_font_atlas: true,
+            evict_textures: true,
+        }
+    }
+}
+
+/// Listen to low memory warnings for the area. The returned handler has to be disconnected when the area is disposed.
+pub(crate) fn watch(area: &EguiArea) -> (gio::MemoryMonitor, glib::SignalHandlerId) {
+    let monitor = gio::MemoryMonitor::dup_default();
+    let handler = monitor.connect_low_memory_warning(glib::clone!(
+        #[weak]
+        area,
+        move |_monitor, level| {
+            let policy = area.imp().low_memory_policy.get();
+            let reached = policy
+                .min_level
+                .is_some_and(|min_level| level.into_glib() >= min_level.into_glib());
+            if reached {
+                let freed = free(&area, policy);
+                area.emit_by_name::<()>("memory-freed", &[&(freed as u64)]);
+            }
+        }
+    ));
+    (monitor, handler)
+}
+
+/// Free memory according to the policy, returning the number of bytes that were freed right away.
+fn free(area: &EguiArea, policy: LowMemoryPolicy) -> usize {
+    let ctx = area.egui_ctx();
+    let mut freed = 0;
+
+    if policy.forget_images {
+        let before = loaders_byte_size(ctx);
+        ctx.forget_all_images();
+        freed += before.saturating_sub(loaders_byte_size(ctx));
+    }
+
+    if policy.evict_textures {
+        freed += area.imp().icons.borrow_mut().evict();
+        freed += area.imp().textures().borrow_mut().evict();
+    }
+
+    // The fonts only exist after the first frame
+    if policy.trim_font_atlas && ctx.frame_nr_for(area.imp().viewport_id()) > 0 {
+        rebuild_fonts(ctx);
+    }
+
+    area.queue_render();
+    freed
+}
+
+/// Build the fonts again on the next frame, which sends a new font atlas with only the glyphs that are used.
+pub(crate) fn rebuild_fonts(ctx: &egui::Context) {
+    // egui only builds the fonts again when the definitions change, so an unused family is added or removed
+    let mut definitions = ctx.fonts(|fonts| fonts.lock().fonts.definitions().clone());
+    let marker = egui::FontFamily::Name("gtk-egui-area-rebuild".into());
+    if definitions.families.remove(&marker).is_none() {
+        definitions.families.insert(marker, Vec::new());
+    }
+    ctx.set_fonts(definitions);
+}
+
+fn loaders_byte_size(ctx: &egui::Context) -> usize {
+    use egui::load::BytesLoader as _;
+
+    let loaders = ctx.loaders();
+    let bytes: usize = loaders
+        .bytes
+        .lock()
+        .iter()
+        .map(|loader| loader.byte_size())
+        .sum();
+    let images: usize = loaders
+        .image
+        .lock()
+        .iter()
+        .map(|loader| loader.byte_size())
+        .sum();
+    let textures: usize = loaders
+        .texture
+        .lock()
+        .iter()
+        .map(|loader| loader.byte_size())
+        .sum();
+    loaders.include.byte_size() + bytes + images + textures
+}
@@ -0,0 +1,217 @@
+use crate::EguiArea;
+use gtk::{
+    gdk, glib, graphene, gsk,
+    prelude::{
+        Cast, GskRendererExt, NativeExt, ObjectExt, PaintableExt, SnapshotExt, TextureExt,
+        TextureExtManual, WidgetExt,
+    },
+};
+use std::{
+    cell::{Cell, RefCell},
+    rc::{Rc, Weak},
+};
+
+/// An egui texture showing the contents of a [`gdk::Paintable`], created with [`EguiArea::paintable_texture`].
+///
+/// The texture follows the paintable, so animated paintables like a playing [`gtk::MediaFile`]
+/// update the texture and repaint the UI on every new frame. The texture is freed when this handle is dropped.
+///
+/// The paintable is drawn into memory, even when its contents are [`gdk::GLTexture`]s,
+/// because GTK doesn't expose the GL names of its textures so they can't be drawn by the egui painter directly.
+pub struct PaintableTexture {
+    inner: Rc<Inner>,
+    handlers: Vec<glib::SignalHandlerId>,
+}
+
+struct Inner {
+    area: glib::WeakRef<EguiArea>,
+    paintable: gdk::Paintable,
+    handle: RefCell<egui::TextureHandle>,
+    /// The intrinsic size of the paintable, in points
+    size: Cell<egui::Vec2>,
+}
+
+impl PaintableTexture {
+    pub(crate) fn new(area: &EguiArea, paintable: gdk::Paintable) -> Self {
+        let handle = area.egui_ctx().load_texture(
+            "gtk-paintable",
+            empty_image(),
+            egui::TextureOptions::LINEAR,
+        );
+
+        let inner = Rc::new(Inner {
+            area: area.downgrade(),
+            paintable: paintable.clone(),
+            handle: RefCell::new(handle),
+            size: Cell::new(egui::Vec2::ZERO),
+        });
+        inner.update();
+        area.set_texture_reload(inner.handle.borrow().id(), {
+            let inner = Rc::downgrade(&inner);
+            move || {
+                if let Some(inner) = inner.upgrade() {
+                    inner.update();
+                }
+            }
+        });
+
+        let handlers = vec![
+            paintable.connect_invalidate_contents(update_handler(&inner)),
+            paintable.connect_invalidate_size(update_handler(&inner)),
+        ];
+
+        Self { inner, handlers }
+    }
+
+    /// The id of the texture.
+    pub fn id(&self) -> egui::TextureId {
+        self.inner.handle.borrow().id()
+    }
+
+    /// The intrinsic size of the paintable, in points.
+    pub fn size(&self) -> egui::Vec2 {
+        self.inner.size.get()
+    }
+
+    /// The texture with its size, for use with [`egui::Image`].
+    pub fn sized_texture(&self) -> egui::load::SizedTexture {
+        egui::load::SizedTexture::new(self.id(), self.size())
+    }
+
+    pub fn paintable(&self) -> &gdk::Paintable {
+        &self.inner.paintable
+    }
+}
+
+impl Drop for PaintableTexture {
+    fn drop(&mut self) {
+        for handler in self.handlers.drain(..) {
+            self.inner.paintable.disconnect(handler);
+        }
+    }
+}
+
+fn update_handler(inner: &Rc<Inner>) -> impl Fn(&gdk::Paintable) + 'static {
+    let inner: Weak<Inner> = Rc::downgrade(inner);
+    move |_| {
+        if let Some(inner) = inner.upgrade() {
+            inner.update();
+        }
+    }
+}
+
+impl Inner {
+    /// Draw the current contents of the paintable into the texture.
+    fn update(&self) {
+        let area = self.area.upgrade();
+        let scale_factor = area
+            .as_ref()
+            .map(|area| crate::scale_factor(area.upcast_ref()))
+            .unwrap_or(1.0);
+
+        let image = self.paintable.current_image();
+        let size = egui::vec2(
+            image.intrinsic_width().max(0) as f32,
+            image.intrinsic_height().max(0) as f32,
+        );
+        self.size.set(size);
+        if size.x < 1.0 || size.y < 1.0 {
+            self.handle
+                .borrow_mut()
+                .set(empty_image(), egui::TextureOptions::LINEAR);
+            return;
+        }
+
+        let texture = match image.downcast::<gdk::Texture>() {
+            Ok(texture) => texture,
+            Err(image) => match render(area.as_ref(), &image, size, scale_factor) {
+                Some(texture) => texture,
+                None => return,
+            },
+        };
+
+        self.handle
+            .borrow_mut()
+            .set(download(&texture), egui::TextureOptions::LINEAR);
+        if let Some(area) = area {
+            area.egui_ctx().request_repaint();
+        }
+    }
+}
+
+fn empty_image() -> egui::ColorImage {
+    egui::ColorImage::new([1, 1], egui::Color32::TRANSPARENT)
+}
+
+/// Render a paintable of the given size in points to a texture, using the renderer of the area if it's realized.
+pub(crate) fn render(
+    area: Option<&EguiArea>,
+    paintable: &gdk::Paintable,
+    size: egui::Vec2,
+    scale_factor: f32,
+) -> Option<gdk::Texture> {
+    let snapshot = gtk::Snapshot::new();
+    snapshot.scale(scale_factor, scale_factor);
+    paintable.snapshot(&snapshot, size.x as f64, size.y as f64);
+    let node = snapshot.to_node()?;
+
+    let viewport = graphene::Rect::new(
+        0.0,
+        0.0,
+        (size.x * scale_factor).ceil(),
+        (size.y * scale_factor).ceil(),
+    );
+
+    let renderer = area
+        .and_then(|area| area.native())
+        .and_then(|native| native.renderer())
+        .filter(|renderer| renderer.is_realized());
+    match renderer {
+        Some(renderer) => Some(renderer.render_texture(&node, Some(&viewport))),
+        None => fallback_renderer().map(|renderer| renderer.render_texture(&node, Some(&viewport))),
+    }
+}
+
+/// A software renderer for drawing paintables before the area is shown.
+fn fallback_renderer() -> Option<gsk::Renderer> {
+    thread_local! {
+        static RENDERER: RefCell<Option<gsk::Renderer>> = const { RefCell::new(None) };
+    }
+
+    RENDERER.with_borrow_mut(|renderer| {
+        if renderer.is_none() {
+            let cairo_renderer = gsk::CairoRenderer::new();
+            match cairo_renderer.realize(None) {
+                Ok(()) => *renderer = Some(cairo_renderer.upcast()),
+                Err(err) => glib::g_warning!(
+                    "gtk-egui-area",
+                    "Could not create a renderer for paintables: {err}"
+                ),
+            }
+        }
+        renderer.clone()
+    })
+}
+
+/// Copy the pixels of a texture into an egui image.
+pub(crate) fn download(texture: &gdk::Texture) -> egui::ColorImage {
+    let width = texture.width() as usize;
+    let height = texture.height() as usize;
+    let stride = width * 4;
+    let mut data = vec![0; stride * height];
+    // The data is in the premultiplied ARGB32 format of cairo, stored as native endian u32s
+    texture.download(&mut data, stride);
+
+    let pixels = data
+        .chunks_exact(4)
+        .map(|pixel| {
+            let [b, g, r, a] = u32::from_ne_bytes(pixel.try_into().unwrap()).to_le_bytes();
+            egui::Color32::from_rgba_premultiplied(r, g, b, a)
+        })
+        .collect();
+
+    egui::ColorImage {
+        size: [width, height],
+        pixels,
+    }
+}
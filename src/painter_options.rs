@@ -0,0 +1,59 @@
+use egui_glow::ShaderVersion;
+#[cfg(feature = "v4_12")]
+use gtk::gdk;
+use gtk::{
+    gdk::GLContext,
+    prelude::{GLAreaExt, GLContextExt},
+};
+
+/// Options for the OpenGL context and the [`egui_glow::Painter`] of an [`crate::EguiArea`].
+///
+/// The defaults let GTK choose the OpenGL API and version, and pick the shader version that matches the created context.
+#[derive(Clone, Debug, Default)]
+pub struct PainterOptions {
+    /// The APIs that GTK may create the context with, e.g. only [`gdk::GLAPI::GLES`] on GLES-only hardware.
+    /// `None` allows both OpenGL and OpenGL ES.
+    #[cfg(feature = "v4_12")]
+    pub allowed_apis: Option<gdk::GLAPI>,
+    /// The minimum version of the context as `(major, minor)`. `None` uses the GTK default.
+    pub required_version: Option<(i32, i32)>,
+    /// The GLSL version that the egui shaders are compiled with.
+    /// `None` picks the version from the API and version of the realized context.
+    pub shader_version: Option<ShaderVersion>,
+    /// Source inserted into the egui shaders after the version declaration, e.g. extra `#define`s.
+    pub shader_prefix: String,
+}
+
+impl PainterOptions {
+    /// Configure the context that `area` creates when it's realized.
+    pub(crate) fn apply(&self, area: &gtk::GLArea) {
+        #[cfg(feature = "v4_12")]
+        area.set_allowed_apis(
+            self.allowed_apis
+                .unwrap_or(gdk::GLAPI::GL | gdk::GLAPI::GLES),
+        );
+        let (major, minor) = self.required_version.unwrap_or((0, 0));
+        area.set_required_version(major, minor);
+    }
+
+    /// The shader version to use with a realized context.
+    pub(crate) fn shader_version(&self, context: &GLContext) -> ShaderVersion {
+        if let Some(shader_version) = self.shader_version {
+            return shader_version;
+        }
+
+        // GLSL 1.40 came with OpenGL 3.1 and GLSL ES 3.00 with OpenGL ES 3.0
+        let version = context.version();
+        if context.uses_es() {
+            if version >= (3, 0) {
+                ShaderVersion::Es300
+            } else {
+                ShaderVersion::Es100
+            }
+        } else if version >= (3, 1) {
+            ShaderVersion::Gl140
+        } else {
+            ShaderVersion::Gl120
+        }
+    }
+}
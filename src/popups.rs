@@ -0,0 +1,242 @@
+use crate::{
+    surface::{SurfaceArea, SurfaceFrame},
+    EguiArea,
+};
+use egui::epaint::ClippedShape;
+use gtk::{
+    gdk, glib,
+    prelude::{ObjectExt, PopoverExt, WidgetExt},
+    subclass::prelude::ObjectSubclassIsExt,
+};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    rc::Rc,
+};
+
+const POPUP_CSS_CLASS: &str = "egui-popup";
+
+/// A popup or tooltip layer that doesn't fit inside of the area.
+pub(crate) struct PopupLayer {
+    pub layer_id: egui::LayerId,
+    pub rect: egui::Rect,
+    pub shapes: Vec<ClippedShape>,
+}
+
+/// The bounds that popups can be placed in: the toplevel window, in area coordinates.
+pub(crate) fn popup_bounds(area: &EguiArea) -> Option<egui::Rect> {
+    let root = area.root()?;
+    let origin = area.compute_point(&root, &gtk::graphene::Point::zero())?;
+    Some(egui::Rect::from_min_size(
+        egui::pos2(-origin.x(), -origin.y()),
+        egui::vec2(root.width() as f32, root.height() as f32),
+    ))
+}
+
+/// Take the shapes of all popup and tooltip layers that don't fit into `screen_rect`.
+///
+/// Must be called at the end of a frame, before the shapes are collected by egui.
+pub(crate) fn take_overflowing_layers(
+    ctx: &egui::Context,
+    screen_rect: egui::Rect,
+) -> Vec<PopupLayer> {
+    let layers: Vec<(egui::LayerId, egui::Rect)> = ctx.memory(|memory| {
+        memory
+            .layer_ids()
+            .filter(|layer_id| {
+                matches!(
+                    layer_id.order,
+                    egui::Order::Foreground | egui::Order::Tooltip
+                ) && memory.areas().is_visible(layer_id)
+            })
+            .filter_map(|layer_id| Some((layer_id, memory.area_rect(layer_id.id)?)))
+            .filter(|(_, rect)| !screen_rect.contains_rect(*rect))
+            .collect()
+    });
+
+    layers
+        .into_iter()
+        .map(|(layer_id, rect)| {
+            let shapes = ctx.graphics_mut(|graphics| {
+                graphics
+                    .get_mut(layer_id)
+                    .map(std::mem::take)
+                    .unwrap_or_default()
+            });
+            PopupLayer {
+                layer_id,
+                rect,
+                shapes: shapes.all_entries().cloned().collect(),
+            }
+        })
+        .collect()
+}
+
+struct Popup {
+    popover: gtk::Popover,
+    surface: SurfaceArea,
+    /// Position of the popup in area coordinates, used to translate input
+    origin: Rc<Cell<egui::Pos2>>,
+}
+
+/// Popovers showing the egui layers that don't fit inside of an [`EguiArea`].
+#[derive(Default)]
+pub(crate) struct Popups {
+    popups: HashMap<egui::LayerId, Popup>,
+}
+
+impl Popups {
+    /// Show `layers` in popovers, hiding the popovers of layers that are gone.
+    pub fn update(&mut self, area: &EguiArea, layers: Vec<PopupLayer>, pixels_per_point: f32) {
+        self.popups.retain(|layer_id, popup| {
+            let keep = layers.iter().any(|layer| layer.layer_id == *layer_id);
+            if !keep {
+                popup.popover.popdown();
+                popup.popover.unparent();
+            }
+            keep
+        });
+
+        for layer in layers {
+            let popup = self
+                .popups
+                .entry(layer.layer_id)
+                .or_insert_with(|| Popup::new(area));
+
+            let rect = layer.rect;
+            popup.origin.set(rect.min);
+            popup.popover.set_pointing_to(Some(&gdk::Rectangle::new(
+                rect.min.x.round() as i32,
+                rect.min.y.round() as i32 - 1,
+                1,
+                1,
+            )));
+            popup
+                .surface
+                .set_size_request(rect.width().ceil() as i32, rect.height().ceil() as i32);
+
+            let offset = -rect.min.to_vec2();
+            let shapes = layer
+                .shapes
+                .into_iter()
+                .map(|mut clipped_shape| {
+                    clipped_shape.shape.translate(offset);
+                    clipped_shape.clip_rect = clipped_shape.clip_rect.translate(offset);
+                    clipped_shape
+                })
+                .collect();
+            popup.surface.set_frame(SurfaceFrame {
+                clipped_primitives: area.egui_ctx().tessellate(shapes, pixels_per_point),
+                pixels_per_point,
+            });
+
+            if !popup.popover.is_visible() {
+                popup.popover.popup();
+            }
+        }
+    }
+
+    /// Hide and remove all popovers.
+    pub fn clear(&mut self) {
+        for (_, popup) in self.popups.drain() {
+            popup.popover.popdown();
+            popup.popover.unparent();
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.popups.is_empty()
+    }
+}
+
+impl Popup {
+    fn new(area: &EguiArea) -> Self {
+        install_css(&area.display());
+
+        let origin = Rc::new(Cell::new(egui::Pos2::ZERO));
+        let area_weak = area.downgrade();
+        let surface = SurfaceArea::new(
+            area.imp().textures(),
+            area.imp().painter_options.borrow().clone(),
+            egui::Color32::TRANSPARENT,
+            Rc::new(glib::clone!(
+                #[strong]
+                origin,
+                move |event| {
+                    if let Some(area) = area_weak.upgrade() {
+                        area.imp()
+                            .push_event(translate_event(event, origin.get().to_vec2()));
+                    }
+                }
+            )),
+        );
+
+        let popover = gtk::Popover::builder()
+            .child(&surface)
+            .has_arrow(false)
+            .autohide(false)
+            .can_focus(false)
+            .position(gtk::PositionType::Bottom)
+            .halign(gtk::Align::Start)
+            .css_classes([POPUP_CSS_CLASS])
+            .build();
+        popover.set_parent(area);
+
+        Self {
+            popover,
+            surface,
+            origin,
+        }
+    }
+}
+
+/// Move the pointer position of an event by `offset`.
+fn translate_event(event: egui::Event, offset: egui::Vec2) -> egui::Event {
+    match event {
+        egui::Event::PointerMoved(pos) => egui::Event::PointerMoved(pos + offset),
+        egui::Event::PointerButton {
+            pos,
+            button,
+            pressed,
+            modifiers,
+        } => egui::Event::PointerButton {
+            pos: pos + offset,
+            button,
+            pressed,
+            modifiers,
+        },
+        event => event,
+    }
+}
+
+/// Remove the popover decorations, so only the egui content is visible.
+fn install_css(display: &gdk::Display) {
+    const CSS: &str = "
+        popover.egui-popup, popover.egui-popup > contents {
+            padding: 0;
+            border: none;
+            box-shadow: none;
+            background: none;
+        }
+    ";
+
+    thread_local! {
+        static STYLED_DISPLAYS: RefCell<Vec<gdk::Display>> = const { RefCell::new(Vec::new()) };
+    }
+
+    STYLED_DISPLAYS.with_borrow_mut(|displays| {
+        if !displays.contains(display) {
+            let provider = gtk::CssProvider::new();
+            #[cfg(feature = "v4_12")]
+            provider.load_from_string(CSS);
+            #[cfg(not(feature = "v4_12"))]
+            provider.load_from_data(CSS);
+            gtk::style_context_add_provider_for_display(
+                display,
+                &provider,
+                gtk::STYLE_PROVIDER_PRIORITY_APPLICATION,
+            );
+            displays.push(display.clone());
+        }
+    });
+}
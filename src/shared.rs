@@ -0,0 +1,207 @@
+use crate::{
+    textures::{SyncedTextures, TextureStore},
+    GioBytesLoader, PainterOptions,
+};
+#[cfg(feature = "v4_12")]
+use gtk::prelude::DisplayExt;
+#[cfg(not(feature = "v4_12"))]
+use gtk::prelude::{NativeExt, SurfaceExt, WidgetExt};
+use gtk::{
+    gdk::{self, GLContext},
+    glib,
+    prelude::{GLAreaExt, GLContextExt},
+};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    rc::{Rc, Weak},
+};
+
+thread_local! {
+    /// The shared state of every egui context used by an area, by the key stored in the context.
+    static CONTEXTS: RefCell<HashMap<u64, Weak<SharedContext>>> = RefCell::new(HashMap::new());
+    static NEXT_KEY: Cell<u64> = const { Cell::new(0) };
+}
+
+/// An egui context and the state that all areas drawing with it share.
+///
+/// egui sends texture changes only with the output of the frame that made them,
+/// so the areas of one context record them in one texture store.
+pub(crate) struct SharedContext {
+    pub ctx: egui::Context,
+    pub textures: Rc<RefCell<TextureStore>>,
+    /// The painters of the areas, one per display
+    painters: RefCell<Vec<Weak<GlPainter>>>,
+    /// The viewports of disposed areas, which new areas run in
+    free_viewports: RefCell<Vec<egui::ViewportId>>,
+    next_viewport: Cell<u64>,
+}
+
+/// An egui painter for a GL context, with the textures that were synced to it.
+///
+/// The painter is destroyed when the last area that draws with it lets go of it.
+pub(crate) struct GlPainter {
+    context: GLContext,
+    pub painter: RefCell<egui_glow::Painter>,
+    pub synced_textures: RefCell<SyncedTextures>,
+}
+
+impl SharedContext {
+    /// The shared state of `ctx`, for a new area that draws with it.
+    pub fn attach(ctx: egui::Context) -> Rc<Self> {
+        let key = ctx.data_mut(|data| {
+            *data.get_temp_mut_or_insert_with(egui::Id::new("gtk-egui-area-context"), || {
+                NEXT_KEY.replace(NEXT_KEY.get() + 1)
+            })
+        });
+
+        CONTEXTS.with_borrow_mut(|contexts| {
+            contexts.retain(|_, shared| shared.strong_count() > 0);
+            if let Some(shared) = contexts.get(&key).and_then(Weak::upgrade) {
+                return shared;
+            }
+
+            GioBytesLoader::install(&ctx);
+            ctx.set_embed_viewports(false);
+            let mut textures = TextureStore::default();
+            // The default texture is the font atlas, which egui sends again when it builds the fonts again
+            textures.set_reload(egui::TextureId::default(), {
+                let ctx = ctx.clone();
+                Rc::new(move || crate::memory::rebuild_fonts(&ctx))
+            });
+            let shared = Rc::new(Self {
+                ctx,
+                textures: Rc::new(RefCell::new(textures)),
+                painters: RefCell::default(),
+                free_viewports: RefCell::default(),
+                next_viewport: Cell::new(0),
+            });
+            contexts.insert(key, Rc::downgrade(&shared));
+            shared
+        })
+    }
+
+    /// A viewport for a new area to run its UI in, which no other area of the context uses.
+    ///
+    /// Areas never run in the root viewport, because egui drops the state of every viewport that didn't run
+    /// when the root viewport ends its frame. That's also the only time when egui drops the state of viewports,
+    /// so the viewports of disposed areas are given to new areas instead, see [`SharedContext::release_viewport`].
+    pub fn acquire_viewport(&self) -> egui::ViewportId {
+        self.free_viewports.borrow_mut().pop().unwrap_or_else(|| {
+            let index = self.next_viewport.replace(self.next_viewport.get() + 1);
+            egui::ViewportId::from_hash_of(("gtk-egui-area", index))
+        })
+    }
+
+    /// Give the viewport of a disposed area to the next new area.
+    pub fn release_viewport(&self, viewport_id: egui::ViewportId) {
+        self.free_viewports.borrow_mut().push(viewport_id);
+    }
+
+    /// The GL context that the areas on `display` draw with, if one of them is realized.
+    pub fn gl_context(&self, display: &gdk::Display) -> Option<GLContext> {
+        self.painters
+            .borrow()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .map(|painter| painter.context.clone())
+            .find(|context| context.display().as_ref() == Some(display))
+    }
+
+    /// The painter for the GL context of a realized area, creating it if no other area draws with that context.
+    pub fn painter(
+        &self,
+        area: &gtk::GLArea,
+        options: &PainterOptions,
+    ) -> Result<Rc<GlPainter>, glib::Error> {
+        let mut painters = self.painters.borrow_mut();
+        painters.retain(|painter| painter.strong_count() > 0);
+
+        let context = area.context();
+        let existing = painters
+            .iter()
+            .filter_map(Weak::upgrade)
+            .find(|painter| Some(&painter.context) == context.as_ref());
+        if let Some(painter) = existing {
+            return Ok(painter);
+        }
+
+        let painter = crate::create_painter(area, options)?;
+        // A painter can only be created with a context
+        let painter = Rc::new(GlPainter {
+            context: context.expect("realized area has no GL context"),
+            painter: RefCell::new(painter),
+            synced_textures: RefCell::default(),
+        });
+        painters.push(Rc::downgrade(&painter));
+        Ok(painter)
+    }
+
+    /// The painter for drawing offscreen on `display`, creating a GL context if no area or paintable
+    /// of this egui context has one there yet.
+    ///
+    /// Without the `v4_12` feature, the context is created for the surface of `widget`.
+    pub fn offscreen_painter(
+        &self,
+        display: &gdk::Display,
+        widget: Option<&gtk::Widget>,
+    ) -> Result<Rc<GlPainter>, glib::Error> {
+        let mut painters = self.painters.borrow_mut();
+        painters.retain(|painter| painter.strong_count() > 0);
+
+        let existing = painters
+            .iter()
+            .filter_map(Weak::upgrade)
+            .find(|painter| painter.context.display().as_ref() == Some(display));
+        if let Some(painter) = existing {
+            return Ok(painter);
+        }
+
+        #[cfg(feature = "v4_12")]
+        let context = {
+            let _ = widget;
+            display.create_gl_context()?
+        };
+        #[cfg(not(feature = "v4_12"))]
+        let context = widget
+            .and_then(|widget| widget.native())
+            .and_then(|native| native.surface())
+            .ok_or_else(|| {
+                glib::Error::new(
+                    gdk::GLError::NotAvailable,
+                    "No surface to create a GL context for",
+                )
+            })?
+            .create_gl_context()?;
+        context.realize()?;
+
+        let painter = crate::create_context_painter(&context, &PainterOptions::default())?;
+        let painter = Rc::new(GlPainter {
+            context,
+            painter: RefCell::new(painter),
+            synced_textures: RefCell::default(),
+        });
+        painters.push(Rc::downgrade(&painter));
+        Ok(painter)
+    }
+
+    /// Stop handing out a painter whose context was lost, so areas realized again get a new context.
+    pub fn discard(&self, painter: &Rc<GlPainter>) {
+        self.painters
+            .borrow_mut()
+            .retain(|other| !std::ptr::eq(other.as_ptr(), Rc::as_ptr(painter)));
+    }
+}
+
+impl GlPainter {
+    pub fn context(&self) -> &GLContext {
+        &self.context
+    }
+}
+
+impl Drop for GlPainter {
+    fn drop(&mut self) {
+        self.context.make_current();
+        self.painter.get_mut().destroy();
+    }
+}
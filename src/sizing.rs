@@ -0,0 +1,83 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+/// The extent of the screen in the directions that the content is measured in during a sizing pass.
+pub(crate) const UNCONSTRAINED: f32 = 100_000.0;
+
+/// The viewport that sizing passes run in, so they don't replace the last frame of the area.
+pub(crate) fn viewport_id() -> egui::ViewportId {
+    egui::ViewportId::from_hash_of("gtk-egui-area-sizing")
+}
+
+/// The natural size reported for a direction in which nothing could be measured, in points.
+pub(crate) const DEFAULT_NATURAL_SIZE: egui::Vec2 = egui::vec2(200.0, 100.0);
+
+/// The size of the content of the last frame of `viewport_id`, which was laid out in a screen of `screen_size`.
+///
+/// Panels and other widgets that fill the available space would be as big as an unconstrained screen,
+/// so in an unconstrained direction, widgets reaching past half of the screen are left out.
+/// Content that is centered or aligned to the far side is left out as well.
+pub(crate) fn content_size(
+    ctx: &egui::Context,
+    viewport_id: egui::ViewportId,
+    screen_size: egui::Vec2,
+) -> egui::Vec2 {
+    let mut limit = egui::Vec2::INFINITY;
+    for axis in 0..2 {
+        if screen_size[axis] >= UNCONSTRAINED {
+            limit[axis] = screen_size[axis] / 2.0;
+        }
+    }
+    let (start, end) = ctx.viewport_for(viewport_id, |viewport| {
+        let mut start = egui::Vec2::splat(f32::INFINITY);
+        let mut end = egui::Vec2::ZERO;
+        for rect in viewport
+            .prev_frame
+            .widgets
+            .layers()
+            .flat_map(|(_, widgets)| widgets.iter().map(|widget| widget.rect))
+        {
+            for axis in 0..2 {
+                if rect.min[axis] >= 0.0 && rect.max[axis] <= limit[axis] {
+                    start[axis] = start[axis].min(rect.min[axis]);
+                    end[axis] = end[axis].max(rect.max[axis]);
+                }
+            }
+        }
+        (start, end)
+    });
+
+    let mut size = egui::Vec2::ZERO;
+    for axis in 0..2 {
+        // egui panels and windows have the same margin on both sides of their content
+        size[axis] = match end[axis] > 0.0 {
+            true => end[axis] + start[axis],
+            false => DEFAULT_NATURAL_SIZE[axis],
+        };
+    }
+    size
+}
+
+/// A hash of the sizes of all widgets in the last frame of `viewport_id`.
+///
+/// It changes when the content changes its size, but not when it's only moved, e.g. by scrolling.
+pub(crate) fn layout_fingerprint(ctx: &egui::Context, viewport_id: egui::ViewportId) -> u64 {
+    ctx.viewport_for(viewport_id, |viewport| {
+        // The layers aren't kept in a stable order, so the hashes of the widgets are combined in any order
+        viewport
+            .prev_frame
+            .widgets
+            .layers()
+            .flat_map(|(_, widgets)| widgets)
+            .map(|widget| {
+                let mut hasher = DefaultHasher::new();
+                widget.id.hash(&mut hasher);
+                widget.rect.width().to_bits().hash(&mut hasher);
+                widget.rect.height().to_bits().hash(&mut hasher);
+                hasher.finish()
+            })
+            .fold(0, u64::wrapping_add)
+    })
+}
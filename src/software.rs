@@ -0,0 +1,241 @@
+use crate::textures::{SyncedTextures, TextureStore, TextureTarget};
+use egui::{
+    epaint::{Mesh, Primitive, Vertex},
+    Color32, ColorImage, ImageData, TextureFilter, TextureId, TextureOptions,
+};
+use gtk::{graphene, prelude::SnapshotExt};
+use std::{collections::HashMap, sync::OnceLock};
+
+/// Setting this environment variable to `software` disables OpenGL rendering.
+const RENDERER_ENV: &str = "GTK_EGUI_AREA_RENDERER";
+
+/// Whether software rendering was requested through the environment.
+pub(crate) fn is_forced() -> bool {
+    static FORCED: OnceLock<bool> = OnceLock::new();
+    *FORCED.get_or_init(|| std::env::var(RENDERER_ENV).is_ok_and(|renderer| renderer == "software"))
+}
+
+/// Draws tessellated egui output on the CPU, for systems without working OpenGL.
+#[derive(Default)]
+pub(crate) struct SoftwarePainter {
+    textures: SoftwareTextures,
+    synced_textures: SyncedTextures,
+    frame: ColorImage,
+}
+
+#[derive(Default)]
+struct SoftwareTextures(HashMap<TextureId, Texture>);
+
+struct Texture {
+    size: [usize; 2],
+    pixels: Vec<Color32>,
+    options: TextureOptions,
+}
+
+impl TextureTarget for SoftwareTextures {
+    fn set_texture(&mut self, id: TextureId, image: &ImageData, options: TextureOptions) {
+        let pixels = match image {
+            ImageData::Color(image) => image.pixels.clone(),
+            ImageData::Font(image) => image.srgba_pixels(None).collect(),
+        };
+        self.0.insert(
+            id,
+            Texture {
+                size: image.size(),
+                pixels,
+                options,
+            },
+        );
+    }
+
+    fn free_texture(&mut self, id: TextureId) {
+        self.0.remove(&id);
+    }
+}
+
+impl SoftwarePainter {
+    /// Draw a frame of `size` physical pixels.
+    pub fn paint(
+        &mut self,
+        textures: &TextureStore,
+        [width, height]: [u32; 2],
+        pixels_per_point: f32,
+        clear_color: Color32,
+        clipped_primitives: &[egui::ClippedPrimitive],
+    ) {
+        textures.sync(&mut self.textures, &mut self.synced_textures);
+
+        let size = [width as usize, height as usize];
+        if self.frame.size == size {
+            self.frame.pixels.fill(clear_color);
+        } else {
+            self.frame = ColorImage::new(size, clear_color);
+        }
+
+        for clipped_primitive in clipped_primitives {
+            // Paint callbacks need OpenGL, so they are skipped
+            let Primitive::Mesh(mesh) = &clipped_primitive.primitive else {
+                continue;
+            };
+            let Some(texture) = self.textures.0.get(&mesh.texture_id) else {
+                continue;
+            };
+
+            let clip_rect = clipped_primitive.clip_rect;
+            let clip = [
+                (clip_rect.min.x * pixels_per_point).round().max(0.0) as usize,
+                (clip_rect.min.y * pixels_per_point).round().max(0.0) as usize,
+                ((clip_rect.max.x * pixels_per_point).round().max(0.0) as usize).min(size[0]),
+                ((clip_rect.max.y * pixels_per_point).round().max(0.0) as usize).min(size[1]),
+            ];
+            paint_mesh(&mut self.frame, clip, mesh, texture, pixels_per_point);
+        }
+    }
+
+    /// The last painted frame.
+    pub fn frame(&self) -> &ColorImage {
+        &self.frame
+    }
+
+    /// Append the last painted frame to a snapshot, covering `width`×`height` in widget coordinates.
+    pub fn snapshot(&self, snapshot: &gtk::Snapshot, width: f32, height: f32) {
+        if self.frame.pixels.is_empty() {
+            return;
+        }
+        snapshot.append_texture(
+            &crate::image_to_texture(&self.frame),
+            &graphene::Rect::new(0.0, 0.0, width, height),
+        );
+    }
+}
+
+fn paint_mesh(
+    frame: &mut ColorImage,
+    [clip_x0, clip_y0, clip_x1, clip_y1]: [usize; 4],
+    mesh: &Mesh,
+    texture: &Texture,
+    pixels_per_point: f32,
+) {
+    let width = frame.size[0];
+
+    for triangle in mesh.indices.chunks_exact(3) {
+        let mut vertices = [0, 1, 2].map(|i| &mesh.vertices[triangle[i] as usize]);
+        let mut points = vertices.map(|vertex| vertex.pos.to_vec2() * pixels_per_point);
+
+        let mut area = edge(points[0], points[1], points[2]);
+        if area == 0.0 {
+            continue;
+        }
+        if area < 0.0 {
+            vertices.swap(1, 2);
+            points.swap(1, 2);
+            area = -area;
+        }
+        let [a, b, c] = points;
+        // Each weight belongs to the vertex opposite of the edge
+        let edges = [(b, c), (c, a), (a, b)];
+
+        let min = a.min(b).min(c);
+        let max = a.max(b).max(c);
+        let x0 = (min.x.floor().max(0.0) as usize).max(clip_x0);
+        let y0 = (min.y.floor().max(0.0) as usize).max(clip_y0);
+        let x1 = (max.x.ceil().max(0.0) as usize).min(clip_x1);
+        let y1 = (max.y.ceil().max(0.0) as usize).min(clip_y1);
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let point = egui::vec2(x as f32 + 0.5, y as f32 + 0.5);
+
+                let mut weights = [0.0; 3];
+                let inside = edges.iter().zip(&mut weights).all(|((from, to), weight)| {
+                    let value = edge(*from, *to, point);
+                    *weight = value / area;
+                    // Pixels exactly on an edge shared by two triangles only belong to one of them
+                    value > 0.0 || (value == 0.0 && is_top_left(*to - *from))
+                });
+                if !inside {
+                    continue;
+                }
+
+                let color = interpolate_color(vertices, weights);
+                let uv = vertices
+                    .iter()
+                    .zip(weights)
+                    .fold(egui::Vec2::ZERO, |uv, (vertex, weight)| {
+                        uv + vertex.uv.to_vec2() * weight
+                    });
+                let texel = texture.sample(uv);
+
+                let source = std::array::from_fn::<f32, 4, _>(|i| color[i] * texel[i]);
+                let pixel = &mut frame.pixels[y * width + x];
+                let destination = pixel.to_array();
+                let blended = std::array::from_fn::<u8, 4, _>(|i| {
+                    let value = source[i] + destination[i] as f32 / 255.0 * (1.0 - source[3]);
+                    (value * 255.0).round().clamp(0.0, 255.0) as u8
+                });
+                *pixel = Color32::from_rgba_premultiplied(
+                    blended[0], blended[1], blended[2], blended[3],
+                );
+            }
+        }
+    }
+}
+
+/// Twice the signed area of the triangle `from`, `to`, `point`.
+fn edge(from: egui::Vec2, to: egui::Vec2, point: egui::Vec2) -> f32 {
+    (to.x - from.x) * (point.y - from.y) - (to.y - from.y) * (point.x - from.x)
+}
+
+/// Exactly one direction of every edge counts as a top-left edge.
+fn is_top_left(direction: egui::Vec2) -> bool {
+    direction.y < 0.0 || (direction.y == 0.0 && direction.x > 0.0)
+}
+
+/// The premultiplied color at a point inside of a triangle, with channels from 0 to 1.
+fn interpolate_color(vertices: [&Vertex; 3], weights: [f32; 3]) -> [f32; 4] {
+    let mut color = [0.0; 4];
+    for (vertex, weight) in vertices.iter().zip(weights) {
+        for (channel, value) in color.iter_mut().zip(vertex.color.to_array()) {
+            *channel += value as f32 / 255.0 * weight;
+        }
+    }
+    color
+}
+
+impl Texture {
+    /// The premultiplied color of the texture at `uv`, with channels from 0 to 1.
+    fn sample(&self, uv: egui::Vec2) -> [f32; 4] {
+        let [width, height] = self.size;
+        if width == 0 || height == 0 {
+            return [0.0; 4];
+        }
+        let texel = |x: isize, y: isize| {
+            let x = x.clamp(0, width as isize - 1) as usize;
+            let y = y.clamp(0, height as isize - 1) as usize;
+            self.pixels[y * width + x]
+                .to_array()
+                .map(|value| value as f32 / 255.0)
+        };
+
+        let x = uv.x * width as f32;
+        let y = uv.y * height as f32;
+        match self.options.magnification {
+            TextureFilter::Nearest => texel(x.floor() as isize, y.floor() as isize),
+            TextureFilter::Linear => {
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (fx, fy) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as isize, y0 as isize);
+
+                let [top_left, top_right, bottom_left, bottom_right] =
+                    [(x0, y0), (x0 + 1, y0), (x0, y0 + 1), (x0 + 1, y0 + 1)]
+                        .map(|(x, y)| texel(x, y));
+                std::array::from_fn(|i| {
+                    let top = top_left[i] + (top_right[i] - top_left[i]) * fx;
+                    let bottom = bottom_left[i] + (bottom_right[i] - bottom_left[i]) * fx;
+                    top + (bottom - top) * fy
+                })
+            }
+        }
+    }
+}
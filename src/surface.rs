@@ -0,0 +1,178 @@
+use crate::{
+    input::{self, EventSink},
+    textures::TextureStore,
+    PainterOptions,
+};
+use gtk::{
+    glib::{self, Object},
+    prelude::{Cast, GLAreaExt},
+    subclass::prelude::ObjectSubclassIsExt,
+};
+use std::{cell::RefCell, rc::Rc};
+
+glib::wrapper! {
+    /// A GL surface that draws egui output produced by an [`crate::EguiArea`],
+    /// used for the parts of the UI that are shown outside of the area itself.
+    pub struct SurfaceArea(ObjectSubclass<imp::SurfaceArea>)
+        @extends gtk::GLArea, gtk::Widget;
+}
+
+/// Tessellated egui output for a [`SurfaceArea`], in surface coordinates.
+#[derive(Default)]
+pub(crate) struct SurfaceFrame {
+    pub clipped_primitives: Vec<egui::ClippedPrimitive>,
+    pub pixels_per_point: f32,
+}
+
+impl SurfaceArea {
+    /// Construct a surface that draws with the textures in `textures` and sends its input to `push_event`.
+    pub(crate) fn new(
+        textures: Rc<RefCell<TextureStore>>,
+        painter_options: PainterOptions,
+        clear_color: egui::Color32,
+        push_event: EventSink,
+    ) -> Self {
+        let surface: Self = Object::builder().build();
+        painter_options.apply(surface.upcast_ref());
+        *surface.imp().painter_options.borrow_mut() = painter_options;
+        *surface.imp().textures.borrow_mut() = textures;
+        surface.imp().clear_color.set(clear_color);
+        input::register_controllers(surface.upcast_ref(), push_event);
+        surface
+    }
+
+    /// Set the color used to clear the surface before drawing.
+    pub(crate) fn set_clear_color(&self, color: egui::Color32) {
+        self.imp().clear_color.set(color);
+        self.queue_render();
+    }
+
+    /// Set the output to draw on the next render.
+    pub(crate) fn set_frame(&self, frame: SurfaceFrame) {
+        *self.imp().frame.borrow_mut() = frame;
+        self.queue_render();
+    }
+}
+
+mod imp {
+    use super::SurfaceFrame;
+    use crate::{
+        software::{self, SoftwarePainter},
+        textures::{SyncedTextures, TextureStore},
+        PainterOptions,
+    };
+    use egui_glow::glow::{self, HasContext};
+    use gtk::{
+        gdk::GLContext,
+        glib,
+        prelude::{Cast, WidgetExt},
+        subclass::{
+            prelude::{GLAreaImpl, GLAreaImplExt, ObjectImpl, ObjectSubclass, ObjectSubclassExt},
+            widget::{WidgetImpl, WidgetImplExt},
+        },
+    };
+    use std::{
+        cell::{Cell, RefCell},
+        rc::Rc,
+    };
+
+    #[derive(Default)]
+    pub struct SurfaceArea {
+        painter: RefCell<Option<egui_glow::Painter>>,
+        software: RefCell<Option<SoftwarePainter>>,
+        pub(super) painter_options: RefCell<PainterOptions>,
+        synced_textures: RefCell<SyncedTextures>,
+        pub(super) textures: RefCell<Rc<RefCell<TextureStore>>>,
+        pub(super) clear_color: Cell<egui::Color32>,
+        pub(super) frame: RefCell<SurfaceFrame>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for SurfaceArea {
+        const NAME: &'static str = "EguiSurfaceArea";
+        type Type = super::SurfaceArea;
+        type ParentType = gtk::GLArea;
+    }
+
+    impl ObjectImpl for SurfaceArea {}
+
+    impl WidgetImpl for SurfaceArea {
+        fn realize(&self) {
+            self.parent_realize();
+
+            // The area that this surface belongs to already warned about OpenGL not working
+            match crate::create_painter(self.obj().upcast_ref(), &self.painter_options.borrow()) {
+                Ok(painter) => *self.painter.borrow_mut() = Some(painter),
+                Err(_) => *self.software.borrow_mut() = Some(SoftwarePainter::default()),
+            }
+        }
+
+        fn unrealize(&self) {
+            self.parent_unrealize();
+            if let Some(mut painter) = self.painter.borrow_mut().take() {
+                painter.destroy();
+            }
+            self.software.borrow_mut().take();
+            self.synced_textures.borrow_mut().clear();
+        }
+
+        fn snapshot(&self, snapshot: &gtk::Snapshot) {
+            match self.software.borrow_mut().as_mut() {
+                Some(software) => {
+                    let obj = self.obj();
+                    let frame = self.frame.borrow();
+                    software.paint(
+                        &self.textures.borrow().borrow(),
+                        crate::native_size(obj.upcast_ref()),
+                        frame.pixels_per_point,
+                        self.clear_color.get(),
+                        &frame.clipped_primitives,
+                    );
+                    software.snapshot(snapshot, obj.width() as f32, obj.height() as f32);
+                }
+                None => self.parent_snapshot(snapshot),
+            }
+        }
+    }
+
+    impl GLAreaImpl for SurfaceArea {
+        fn create_context(&self) -> Option<GLContext> {
+            if software::is_forced() {
+                None
+            } else {
+                self.parent_create_context()
+            }
+        }
+
+        fn render(&self, _context: &GLContext) -> glib::Propagation {
+            let screen_size_pixels = crate::native_size(self.obj().upcast_ref());
+
+            let mut painter_guard = self.painter.borrow_mut();
+            let Some(painter) = painter_guard.as_mut() else {
+                return glib::Propagation::Stop;
+            };
+            painter.clear(
+                screen_size_pixels,
+                self.clear_color.get().to_normalized_gamma_f32(),
+            );
+
+            self.textures
+                .borrow()
+                .borrow()
+                .sync(painter, &mut self.synced_textures.borrow_mut());
+
+            let frame = self.frame.borrow();
+            painter.paint_primitives(
+                screen_size_pixels,
+                frame.pixels_per_point,
+                &frame.clipped_primitives,
+            );
+
+            if unsafe { painter.gl().get_error() } == glow::CONTEXT_LOST {
+                crate::replace_lost_context(self.obj().upcast_ref());
+            }
+
+            glib::Propagation::Stop
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use egui::{epaint::ImageDelta, ImageData, TextureId, TextureOptions, TexturesDelta};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
+/// CPU-side copy of all textures that egui considers alive.
+///
+/// egui only sends texture changes once, so this is used to upload the full set of textures to painters
+/// that were created after the textures were first sent.
+///
+/// The copies of textures that can be set again are evicted under memory pressure,
+/// and reloaded once a painter needs them.
+#[derive(Default)]
+pub(crate) struct TextureStore {
+    textures: HashMap<TextureId, StoredTexture>,
+    next_version: u64,
+    /// Functions that set a texture again after its copy was evicted
+    reloads: HashMap<TextureId, Rc<dyn Fn()>>,
+    /// The textures whose reload is scheduled
+    reloading: Rc<RefCell<HashSet<TextureId>>>,
+}
+
+struct StoredTexture {
+    /// `None` once the copy was evicted, until the texture is set again
+    image: Option<ImageData>,
+    options: TextureOptions,
+    version: u64,
+}
+
+/// Texture versions that were uploaded to a painter, used to find out which textures are outdated.
+pub(crate) type SyncedTextures = HashMap<TextureId, u64>;
+
+/// A painter that textures can be uploaded to.
+pub(crate) trait TextureTarget {
+    fn set_texture(&mut self, id: TextureId, image: &ImageData, options: TextureOptions);
+    fn free_texture(&mut self, id: TextureId);
+}
+
+impl TextureTarget for egui_glow::Painter {
+    fn set_texture(&mut self, id: TextureId, image: &ImageData, options: TextureOptions) {
+        egui_glow::Painter::set_texture(self, id, &ImageDelta::full(image.clone(), options));
+    }
+
+    fn free_texture(&mut self, id: TextureId) {
+        egui_glow::Painter::free_texture(self, id);
+    }
+}
+
+impl TextureStore {
+    /// Record the changes in a texture delta.
+    pub fn apply(&mut self, delta: &TexturesDelta) {
+        let mut evicted = Vec::new();
+        for (id, image_delta) in &delta.set {
+            self.next_version += 1;
+            let version = self.next_version;
+
+            match (image_delta.pos, self.textures.get_mut(id)) {
+                (Some(pos), Some(stored)) => {
+                    match &mut stored.image {
+                        Some(image) => patch_image(image, pos, &image_delta.image),
+                        // The rest of the texture is needed to upload the change
+                        None => evicted.push(*id),
+                    }
+                    stored.options = image_delta.options;
+                    stored.version = version;
+                }
+                (Some(_), None) => {
+                    gtk::glib::g_warning!(
+                        "gtk-egui-area",
+                        "Partial texture update for unknown texture {id:?}"
+                    );
+                }
+                (None, _) => {
+                    self.textures.insert(
+                        *id,
+                        StoredTexture {
+                            image: Some(image_delta.image.clone()),
+                            options: image_delta.options,
+                            version,
+                        },
+                    );
+                }
+            }
+        }
+
+        for id in &delta.free {
+            self.textures.remove(id);
+            self.reloads.remove(id);
+        }
+
+        for id in evicted {
+            self.reload(id);
+        }
+    }
+
+    /// Set a function that sets the texture `id` again, so its copy can be evicted.
+    pub fn set_reload(&mut self, id: TextureId, reload: Rc<dyn Fn()>) {
+        self.reloads.insert(id, reload);
+    }
+
+    /// Drop the copies of all textures that can be set again, returning the number of bytes they used.
+    pub fn evict(&mut self) -> usize {
+        self.textures
+            .iter_mut()
+            .filter(|(id, _)| self.reloads.contains_key(id))
+            .filter_map(|(_, stored)| stored.image.take())
+            .map(|image| image.width() * image.height() * image.bytes_per_pixel())
+            .sum()
+    }
+
+    /// Set the texture again once GTK is idle, which sends its full image with the next frame.
+    fn reload(&self, id: TextureId) {
+        let Some(reload) = self.reloads.get(&id).cloned() else {
+            return;
+        };
+        if !self.reloading.borrow_mut().insert(id) {
+            return;
+        }
+
+        // The reload may use the egui context or the area, which may be borrowed while painting
+        let reloading = self.reloading.clone();
+        gtk::glib::idle_add_local_once(move || {
+            reloading.borrow_mut().remove(&id);
+            reload();
+        });
+    }
+
+    /// Make the textures of a painter match the stored ones, uploading everything that changed since the last sync.
+    pub fn sync(&self, painter: &mut impl TextureTarget, synced: &mut SyncedTextures) {
+        for (id, stored) in &self.textures {
+            if synced.get(id) != Some(&stored.version) {
+                match &stored.image {
+                    Some(image) => {
+                        painter.set_texture(*id, image, stored.options);
+                        synced.insert(*id, stored.version);
+                    }
+                    None => self.reload(*id),
+                }
+            }
+        }
+
+        synced.retain(|id, _| {
+            let alive = self.textures.contains_key(id);
+            if !alive {
+                painter.free_texture(*id);
+            }
+            alive
+        });
+    }
+}
+
+fn patch_image(image: &mut ImageData, [x, y]: [usize; 2], patch: &ImageData) {
+    fn copy_rows<T: Copy>(
+        pixels: &mut [T],
+        width: usize,
+        [x, y]: [usize; 2],
+        patch: &[T],
+        [patch_width, patch_height]: [usize; 2],
+    ) {
+        for row in 0..patch_height {
+            let start = (y + row) * width + x;
+            pixels[start..start + patch_width]
+                .copy_from_slice(&patch[row * patch_width..(row + 1) * patch_width]);
+        }
+    }
+
+    match (image, patch) {
+        (ImageData::Color(image), ImageData::Color(patch)) => {
+            let image = std::sync::Arc::make_mut(image);
+            copy_rows(
+                &mut image.pixels,
+                image.size[0],
+                [x, y],
+                &patch.pixels,
+                patch.size,
+            );
+        }
+        (ImageData::Font(image), ImageData::Font(patch)) => {
+            copy_rows(
+                &mut image.pixels,
+                image.size[0],
+                [x, y],
+                &patch.pixels,
+                patch.size,
+            );
+        }
+        _ => {
+            gtk::glib::g_warning!(
+                "gtk-egui-area",
+                "Partial texture update with a different image type"
+            );
+        }
+    }
+}
@@ -0,0 +1,398 @@
+use crate::{
+    embed,
+    surface::{SurfaceArea, SurfaceFrame},
+    EguiArea,
+};
+use gtk::{
+    glib::{self, clone},
+    prelude::{Cast, CastNone, GtkWindowExt, ObjectExt, WidgetExt, WidgetExtManual},
+    subclass::prelude::ObjectSubclassIsExt,
+};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    rc::Rc,
+    sync::Arc,
+};
+
+/// Make immediate egui viewports open windows of the area that is currently running its UI.
+///
+/// The renderer is stored per thread by egui, so installing it again is harmless.
+pub(crate) fn install_immediate_renderer() {
+    egui::Context::set_immediate_viewport_renderer(|_ctx, viewport| match embed::current_area() {
+        Some(area) => {
+            area.imp().viewports.borrow_mut().ensure_window(
+                &area,
+                viewport.ids.this,
+                viewport.ids.parent,
+                egui::ViewportClass::Immediate,
+                viewport.builder,
+            );
+            run_viewport(&area, viewport.ids.this, viewport.viewport_ui_cb);
+        }
+        None => glib::g_warning!(
+            "gtk-egui-area",
+            "Trying to show an immediate viewport outside of an EguiArea UI function"
+        ),
+    });
+}
+
+/// Run a frame of a deferred viewport with its stored UI callback.
+fn run_deferred(area: &EguiArea, id: egui::ViewportId) {
+    let ui_cb = area
+        .imp()
+        .viewports
+        .borrow()
+        .windows
+        .get(&id)
+        .and_then(|window| window.ui_cb.clone());
+
+    if let Some(ui_cb) = ui_cb {
+        run_viewport(area, id, |ctx| ui_cb(ctx));
+    }
+}
+
+/// Run a frame of a viewport window and show the output in it.
+fn run_viewport(area: &EguiArea, id: egui::ViewportId, run_ui: impl FnOnce(&egui::Context)) {
+    let imp = area.imp();
+    let raw_input = imp.viewports.borrow().raw_input(id, imp.viewport_infos());
+    let Some(mut raw_input) = raw_input else {
+        return;
+    };
+    (raw_input.time, raw_input.predicted_dt) = imp.frame_timing();
+
+    let screen_rect = raw_input.screen_rect;
+
+    let full_output = embed::with_current_area(area, || area.egui_ctx().run(raw_input, run_ui));
+    let textures_changed = !full_output.textures_delta.is_empty();
+    let pixels_per_point = full_output.pixels_per_point;
+    let shapes = imp.handle_output(full_output);
+
+    let viewports = imp.viewports.borrow();
+    let Some(window) = viewports.windows.get(&id) else {
+        return;
+    };
+    let content = WindowContent {
+        shapes,
+        pixels_per_point,
+        screen_rect,
+    };
+    // The window keeps showing its last frame, so frames that look the same are neither tessellated nor rendered
+    if !textures_changed && window.drawn_content.borrow().as_ref() == Some(&content) {
+        return;
+    }
+
+    let clipped_primitives = area
+        .egui_ctx()
+        .tessellate(content.shapes.clone(), pixels_per_point);
+    window.drawn_content.replace(Some(content));
+    window.surface.set_frame(SurfaceFrame {
+        clipped_primitives,
+        pixels_per_point,
+    });
+}
+
+/// The viewports in the output of a frame that descend from the viewport `root` of an area.
+pub(crate) fn children_of(
+    root: egui::ViewportId,
+    mut output: egui::ViewportIdMap<egui::ViewportOutput>,
+) -> egui::ViewportIdMap<egui::ViewportOutput> {
+    let descends = |mut id: egui::ViewportId| {
+        // Parents are followed at most once per viewport, in case egui reports a cycle
+        for _ in 0..output.len() {
+            match output.get(&id) {
+                Some(viewport) if viewport.parent == root => return true,
+                Some(viewport) if viewport.parent != id => id = viewport.parent,
+                _ => return false,
+            }
+        }
+        false
+    };
+    let children: Vec<egui::ViewportId> = output
+        .keys()
+        .copied()
+        .filter(|id| *id != root && descends(*id))
+        .collect();
+    output.retain(|id, _| children.contains(id));
+    output
+}
+
+/// Everything that decides what a frame of a viewport window looks like, apart from the textures.
+#[derive(PartialEq)]
+struct WindowContent {
+    shapes: Vec<egui::epaint::ClippedShape>,
+    pixels_per_point: f32,
+    screen_rect: Option<egui::Rect>,
+}
+
+/// A GTK window showing an egui viewport.
+struct ViewportWindow {
+    window: gtk::Window,
+    surface: SurfaceArea,
+    parent: egui::ViewportId,
+    /// The builder that the window currently matches
+    builder: egui::ViewportBuilder,
+    ui_cb: Option<Arc<egui::DeferredViewportUiCallback>>,
+    events: Rc<RefCell<Vec<egui::Event>>>,
+    close_requested: Rc<Cell<bool>>,
+    drawn_content: RefCell<Option<WindowContent>>,
+}
+
+/// The windows of the child viewports of an [`EguiArea`].
+#[derive(Default)]
+pub(crate) struct Viewports {
+    windows: HashMap<egui::ViewportId, ViewportWindow>,
+}
+
+impl Viewports {
+    /// The state of all viewport windows, as reported to egui.
+    pub fn infos(&self) -> egui::ViewportIdMap<egui::ViewportInfo> {
+        self.windows
+            .iter()
+            .map(|(id, window)| (*id, window.info()))
+            .collect()
+    }
+
+    /// The input for a frame of the viewport `id`, or `None` if it has no window.
+    fn raw_input(
+        &self,
+        id: egui::ViewportId,
+        mut viewports: egui::ViewportIdMap<egui::ViewportInfo>,
+    ) -> Option<egui::RawInput> {
+        let window = self.windows.get(&id)?;
+
+        if window.close_requested.take() {
+            if let Some(info) = viewports.get_mut(&id) {
+                info.events.push(egui::ViewportEvent::Close);
+            }
+        }
+
+        let focused = window.window.is_active();
+        Some(egui::RawInput {
+            viewport_id: id,
+            viewports,
+            events: std::mem::take(window.events.borrow_mut().as_mut()),
+            screen_rect: Some(egui::Rect::from_min_size(
+                egui::Pos2::ZERO,
+                egui::vec2(
+                    window.surface.width() as f32,
+                    window.surface.height() as f32,
+                ),
+            )),
+            focused,
+            ..egui::RawInput::default()
+        })
+    }
+
+    /// Create a window for the viewport `id` if it doesn't have one yet.
+    fn ensure_window(
+        &mut self,
+        area: &EguiArea,
+        id: egui::ViewportId,
+        parent: egui::ViewportId,
+        class: egui::ViewportClass,
+        builder: egui::ViewportBuilder,
+    ) {
+        if !self.windows.contains_key(&id) {
+            let window = ViewportWindow::new(area, id, parent, class, builder, self);
+            self.windows.insert(id, window);
+        }
+    }
+
+    /// Open, update and close windows to match the viewports that egui output in a frame.
+    pub fn update(&mut self, area: &EguiArea, output: egui::ViewportIdMap<egui::ViewportOutput>) {
+        self.windows.retain(|id, window| {
+            let keep = output.contains_key(id);
+            if !keep {
+                window.window.destroy();
+            }
+            keep
+        });
+
+        for (id, viewport) in output {
+            if viewport.class == egui::ViewportClass::Embedded {
+                continue;
+            }
+
+            self.ensure_window(
+                area,
+                id,
+                viewport.parent,
+                viewport.class,
+                viewport.builder.clone(),
+            );
+
+            let parent_window = self.parent_window(area, viewport.parent);
+            let window = self.windows.get_mut(&id).unwrap();
+            window.ui_cb = viewport.viewport_ui_cb;
+            window.parent = viewport.parent;
+
+            let (mut commands, _recreate) = window.builder.patch(viewport.builder);
+            commands.extend(viewport.commands);
+
+            if commands.contains(&egui::ViewportCommand::Close) {
+                window.window.destroy();
+                self.windows.remove(&id);
+                continue;
+            }
+            for command in commands {
+                window.apply_command(command, parent_window.as_ref());
+            }
+        }
+    }
+
+    /// Close all viewport windows.
+    pub fn clear(&mut self) {
+        for (_, window) in self.windows.drain() {
+            window.window.destroy();
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.windows.is_empty()
+    }
+
+    /// The window that shows the viewport `parent`.
+    fn parent_window(&self, area: &EguiArea, parent: egui::ViewportId) -> Option<gtk::Window> {
+        match self.windows.get(&parent) {
+            Some(window) => Some(window.window.clone()),
+            None => area.root().and_downcast::<gtk::Window>(),
+        }
+    }
+}
+
+impl ViewportWindow {
+    fn new(
+        area: &EguiArea,
+        id: egui::ViewportId,
+        parent: egui::ViewportId,
+        class: egui::ViewportClass,
+        builder: egui::ViewportBuilder,
+        viewports: &Viewports,
+    ) -> Self {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let surface = SurfaceArea::new(
+            area.imp().textures(),
+            area.imp().painter_options.borrow().clone(),
+            area.egui_ctx().style().visuals.window_fill(),
+            Rc::new(clone!(
+                #[strong]
+                events,
+                move |event| events.borrow_mut().push(event)
+            )),
+        );
+        surface.set_focusable(true);
+        surface.set_hexpand(true);
+        surface.set_vexpand(true);
+
+        let window = gtk::Window::builder().child(&surface).build();
+        if let Some(close_button) = builder.close_button {
+            window.set_deletable(close_button);
+        }
+
+        let close_requested = Rc::new(Cell::new(false));
+        window.connect_close_request(clone!(
+            #[strong]
+            close_requested,
+            move |_| {
+                // egui decides if the viewport closes, by not showing it anymore
+                close_requested.set(true);
+                glib::Propagation::Stop
+            }
+        ));
+
+        if class == egui::ViewportClass::Deferred {
+            let area = area.downgrade();
+            // Frame times are in microseconds
+            let last_frame_time = Cell::new(None::<i64>);
+            surface.add_tick_callback(move |_, frame_clock| {
+                if let Some(area) = area.upgrade() {
+                    // The window follows the frame rate policy of its area
+                    if area
+                        .imp()
+                        .render_due(&last_frame_time, frame_clock.frame_time())
+                    {
+                        run_deferred(&area, id);
+                    }
+                }
+                glib::ControlFlow::Continue
+            });
+        }
+
+        let mut viewport_window = Self {
+            window,
+            surface,
+            parent,
+            builder: egui::ViewportBuilder::default(),
+            ui_cb: None,
+            events,
+            close_requested,
+            drawn_content: RefCell::new(None),
+        };
+
+        let parent_window = viewports.parent_window(area, parent);
+        let (commands, _recreate) = viewport_window.builder.patch(builder);
+        let visible = !commands.contains(&egui::ViewportCommand::Visible(false));
+        for command in commands {
+            viewport_window.apply_command(command, parent_window.as_ref());
+        }
+
+        if visible {
+            // The window may be created while GTK is drawing, so it is only shown afterwards
+            let window = viewport_window.window.downgrade();
+            glib::idle_add_local_once(move || {
+                if let Some(window) = window.upgrade() {
+                    window.present();
+                }
+            });
+        }
+
+        viewport_window
+    }
+
+    fn info(&self) -> egui::ViewportInfo {
+        let size = egui::vec2(self.surface.width() as f32, self.surface.height() as f32);
+        egui::ViewportInfo {
+            parent: Some(self.parent),
+            title: self.window.title().map(Into::into),
+            native_pixels_per_point: Some(crate::scale_factor(self.surface.upcast_ref())),
+            inner_rect: Some(egui::Rect::from_min_size(egui::Pos2::ZERO, size)),
+            maximized: Some(self.window.is_maximized()),
+            fullscreen: Some(self.window.is_fullscreen()),
+            focused: Some(self.window.is_active()),
+            ..Default::default()
+        }
+    }
+
+    /// Apply a viewport command to the window. Commands that GTK has no equivalent for are ignored.
+    fn apply_command(&self, command: egui::ViewportCommand, parent_window: Option<&gtk::Window>) {
+        use egui::ViewportCommand;
+
+        let window = &self.window;
+        match command {
+            ViewportCommand::Title(title) => window.set_title(Some(&title)),
+            ViewportCommand::Visible(visible) => window.set_visible(visible),
+            ViewportCommand::InnerSize(size) => {
+                window.set_default_size(size.x.round() as i32, size.y.round() as i32)
+            }
+            ViewportCommand::MinInnerSize(size) => self
+                .surface
+                .set_size_request(size.x.round() as i32, size.y.round() as i32),
+            ViewportCommand::Resizable(resizable) => window.set_resizable(resizable),
+            ViewportCommand::Decorations(decorations) => window.set_decorated(decorations),
+            ViewportCommand::Transparent(true) => {
+                self.surface.set_clear_color(egui::Color32::TRANSPARENT)
+            }
+            ViewportCommand::Minimized(true) => window.minimize(),
+            ViewportCommand::Minimized(false) | ViewportCommand::Focus => window.present(),
+            ViewportCommand::Maximized(maximized) => window.set_maximized(maximized),
+            ViewportCommand::Fullscreen(fullscreen) => window.set_fullscreened(fullscreen),
+            // GTK 4 can't raise windows above others, so the closest match is keeping the window above its parent
+            ViewportCommand::WindowLevel(egui::WindowLevel::AlwaysOnTop) => {
+                window.set_transient_for(parent_window)
+            }
+            ViewportCommand::WindowLevel(_) => window.set_transient_for(None::<&gtk::Window>),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,10 @@
+//! Setup shared by the tests that need a display.
+
+/// Initialize GTK, failing the test when there is no display to connect to.
+///
+/// Tests that call this are ignored by default and run with `cargo test -- --ignored`.
+pub fn init_gtk() {
+    if let Err(error) = gtk::init() {
+        panic!("GTK could not be initialized, these tests need a display: {error}");
+    }
+}
@@ -0,0 +1,58 @@
+//! An area that is moved to another window is realized again with a new GL context,
+//! which has to get all textures that egui uploaded before.
+
+mod common;
+
+use gtk::{glib, prelude::*};
+use gtk_egui_area::{egui, EguiArea};
+use std::cell::OnceCell;
+
+#[test]
+#[ignore = "needs a display"]
+fn textures_survive_reparenting() {
+    common::init_gtk();
+
+    let texture = OnceCell::new();
+    let area = EguiArea::new(move |ctx| {
+        // The texture is only sent to the painter in the first frame
+        let texture: &egui::TextureHandle = texture.get_or_init(|| {
+            let image = egui::ColorImage::new([4, 4], egui::Color32::RED);
+            ctx.load_texture("red", image, egui::TextureOptions::NEAREST)
+        });
+        egui::CentralPanel::default()
+            .frame(egui::Frame::none())
+            .show(ctx, |ui| {
+                ui.image((texture.id(), ui.available_size()));
+            });
+    });
+    area.set_clear_color(Some(egui::Color32::BLUE));
+
+    let first_window = gtk::Window::new();
+    first_window.set_default_size(64, 64);
+    first_window.set_child(Some(&area));
+    first_window.present();
+    assert_center_is_red(&area);
+
+    first_window.set_child(None::<&gtk::Widget>);
+    assert!(!area.is_realized());
+
+    let second_window = gtk::Window::new();
+    second_window.set_default_size(64, 64);
+    second_window.set_child(Some(&area));
+    second_window.present();
+    assert_center_is_red(&area);
+
+    first_window.destroy();
+    second_window.destroy();
+}
+
+fn assert_center_is_red(area: &EguiArea) {
+    let image = glib::MainContext::default()
+        .block_on(area.capture())
+        .expect("The area was not rendered");
+    let [width, height] = image.size;
+    assert_eq!(
+        image.pixels[height / 2 * width + width / 2],
+        egui::Color32::RED
+    );
+}
@@ -0,0 +1,43 @@
+//! Areas inside of a scrolled window must be scrollable, also without a size function.
+
+mod common;
+
+use gtk::{glib, prelude::*};
+use gtk_egui_area::{egui, EguiArea};
+
+// GTK can only be used from one thread, so the checks run in a single test
+#[test]
+#[ignore = "needs a display"]
+fn scrolling() {
+    common::init_gtk();
+
+    scrolls_without_size_fn();
+}
+
+fn scrolls_without_size_fn() {
+    let area = EguiArea::new(|ctx| {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            for index in 0..100 {
+                ui.label(format!("Label {index}"));
+            }
+        });
+    });
+
+    let scrolled_window = gtk::ScrolledWindow::builder()
+        .hscrollbar_policy(gtk::PolicyType::Never)
+        .child(&area)
+        .build();
+    let window = gtk::Window::new();
+    window.set_default_size(200, 200);
+    window.set_child(Some(&scrolled_window));
+    window.present();
+    glib::MainContext::default().block_on(area.capture());
+
+    let vadjustment = scrolled_window.vadjustment();
+    assert!(
+        vadjustment.upper() > vadjustment.page_size(),
+        "The content of the area can't be scrolled"
+    );
+
+    window.destroy();
+}
@@ -0,0 +1,80 @@
+//! Areas must be freed once their window is gone, together with everything that their UI used.
+
+mod common;
+
+use gtk::{glib, prelude::*};
+use gtk_egui_area::{egui, EguiArea, UiExt};
+
+// GTK can only be used from one thread, so the checks run in a single test
+#[test]
+#[ignore = "needs a display"]
+fn teardown() {
+    common::init_gtk();
+
+    area_is_finalized();
+    dispose_releases_ui();
+}
+
+fn area_is_finalized() {
+    let (area, button) = {
+        let button = gtk::Button::with_label("Embedded");
+        let area = EguiArea::new(glib::clone!(
+            #[strong]
+            button,
+            move |ctx| {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    ui.label("Hello");
+                    ui.gtk_widget(&button, egui::vec2(100.0, 30.0));
+                    ui.gtk_icon("image-missing", 16.0);
+                });
+            }
+        ));
+        area.set_external_popups(true);
+        area.set_pre_render(|_gl, _info| {});
+
+        let window = gtk::Window::new();
+        window.set_default_size(200, 200);
+        window.set_child(Some(&area));
+        window.present();
+        glib::MainContext::default().block_on(area.capture());
+        window.destroy();
+
+        (area.downgrade(), button.downgrade())
+    };
+
+    let context = glib::MainContext::default();
+    while context.iteration(false) {}
+
+    assert!(area.upgrade().is_none(), "The area was not freed");
+    assert!(
+        button.upgrade().is_none(),
+        "The widget captured by the UI function was not freed"
+    );
+}
+
+fn dispose_releases_ui() {
+    // A UI function that captures the area creates a cycle, which dispose has to break
+    let area = EguiArea::default();
+    let label = gtk::Label::new(None);
+    area.set_ui(glib::clone!(
+        #[strong]
+        area,
+        #[strong]
+        label,
+        move |ctx| {
+            let _ = (&area, &label);
+            egui::CentralPanel::default().show(ctx, |_ui| {});
+        }
+    ));
+
+    let label = label.downgrade();
+    let weak_area = area.downgrade();
+    unsafe { area.run_dispose() };
+    drop(area);
+
+    assert!(weak_area.upgrade().is_none(), "The area was not freed");
+    assert!(
+        label.upgrade().is_none(),
+        "The widget captured by the UI function was not freed"
+    );
+}